@@ -0,0 +1,206 @@
+use crate::Point;
+
+/// Affine mapping of raw touchscreen readings to framebuffer coordinates.
+///
+/// The result is normalized (`0.0..=1.0` spans the whole framebuffer), so the
+/// same calibration works for any resolution of the panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchCalibration {
+    /// `[a, b, c, d, e, f]` where `x = a * raw_x + b * raw_y + c` and
+    /// `y = d * raw_x + e * raw_y + f`
+    pub matrix: [f64; 6],
+}
+
+impl Default for TouchCalibration {
+    /// Calibration of the original 3.5" panel (touch axes swapped).
+    fn default() -> Self {
+        let (min_x, max_x, min_y, max_y) = (238.0, 3996.0, 3931.0, 173.0);
+        Self {
+            matrix: [
+                0.0,
+                1.0 / (max_y - min_y),
+                -min_y / (max_y - min_y),
+                1.0 / (max_x - min_x),
+                0.0,
+                -min_x / (max_x - min_x),
+            ],
+        }
+    }
+}
+
+impl TouchCalibration {
+    pub fn new(matrix: [f64; 6]) -> Self {
+        Self { matrix }
+    }
+
+    /// Solves the calibration from `(raw, target)` pairs with least squares,
+    /// targets are normalized. At least 3 points, not on a single line, are
+    /// needed.
+    pub fn from_samples(samples: &[(Point, Point)]) -> Option<Self> {
+        if samples.len() < 3 {
+            return None;
+        }
+
+        // Raw readings are centered and scaled to -1..=1, otherwise entries
+        // of the system are too large to tell whether it is singular.
+        let n = samples.len() as f64;
+        let mean_x = samples.iter().map(|(raw, _)| raw.x).sum::<f64>() / n;
+        let mean_y = samples.iter().map(|(raw, _)| raw.y).sum::<f64>() / n;
+        let scale = samples
+            .iter()
+            .map(|(raw, _)| (raw.x - mean_x).abs().max((raw.y - mean_y).abs()))
+            .fold(0.0, f64::max);
+        if scale == 0.0 {
+            return None;
+        }
+
+        // Normal equations: sum([u v 1]^T [u v 1]) * p = sum([u v 1]^T * target)
+        let mut m = [[0.0f64; 3]; 3];
+        let mut rhs_x = [0.0f64; 3];
+        let mut rhs_y = [0.0f64; 3];
+        for (raw, target) in samples {
+            let v = [(raw.x - mean_x) / scale, (raw.y - mean_y) / scale, 1.0];
+            for i in 0..3 {
+                for j in 0..3 {
+                    m[i][j] += v[i] * v[j];
+                }
+                rhs_x[i] += v[i] * target.x;
+                rhs_y[i] += v[i] * target.y;
+            }
+        }
+
+        let x = solve3(&m, &rhs_x)?;
+        let y = solve3(&m, &rhs_y)?;
+        // Back to raw readings: a * (raw - mean) / scale + c
+        let unscale = |p: [f64; 3]| {
+            let (a, b) = (p[0] / scale, p[1] / scale);
+            [a, b, p[2] - a * mean_x - b * mean_y]
+        };
+        let (x, y) = (unscale(x), unscale(y));
+        Some(Self {
+            matrix: [x[0], x[1], x[2], y[0], y[1], y[2]],
+        })
+    }
+
+    /// Maps a raw reading to coordinates of a framebuffer of the given size.
+    pub fn map(&self, raw: &Point, width: f64, height: f64) -> Point {
+        let [a, b, c, d, e, f] = self.matrix;
+        Point {
+            x: (a * raw.x + b * raw.y + c) * width,
+            y: (d * raw.x + e * raw.y + f) * height,
+        }
+    }
+}
+
+fn det3(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// Smallest determinant of a system relative to the cube of its largest
+/// entry, below it samples are (nearly) on a single line.
+const MIN_RELATIVE_DET: f64 = 1e-3;
+
+/// Cramer's rule, `None` for (nearly) singular systems.
+fn solve3(m: &[[f64; 3]; 3], rhs: &[f64; 3]) -> Option<[f64; 3]> {
+    let det = det3(m);
+    let norm = m.iter().flatten().fold(0.0f64, |max, v| max.max(v.abs()));
+    if det.abs() <= MIN_RELATIVE_DET * norm.powi(3) {
+        return None;
+    }
+
+    let mut result = [0.0; 3];
+    for (col, r) in result.iter_mut().enumerate() {
+        let mut mc = *m;
+        for row in 0..3 {
+            mc[row][col] = rhs[row];
+        }
+        *r = det3(&mc) / det;
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration_solves_affine_transform() {
+        // Swapped, mirrored and slightly skewed panel
+        let truth = TouchCalibration::new([0.0002, -0.00025, 0.9, 0.00026, 0.00001, -0.05]);
+        let samples: Vec<(Point, Point)> = [(300.0, 400.0), (3800.0, 350.0), (320.0, 3700.0)]
+            .iter()
+            .chain([(3750.0, 3650.0), (2000.0, 2100.0)].iter())
+            .map(|&(x, y)| {
+                let raw = Point { x, y };
+                let target = truth.map(&raw, 1.0, 1.0);
+                (raw, target)
+            })
+            .collect();
+
+        let solved = TouchCalibration::from_samples(&samples).unwrap();
+        for (s, t) in solved.matrix.iter().zip(truth.matrix.iter()) {
+            assert!((s - t).abs() < 1e-9, "{:?} vs {:?}", solved, truth);
+        }
+
+        let p = solved.map(
+            &Point {
+                x: 2000.0,
+                y: 2100.0,
+            },
+            480.0,
+            320.0,
+        );
+        let q = truth.map(
+            &Point {
+                x: 2000.0,
+                y: 2100.0,
+            },
+            480.0,
+            320.0,
+        );
+        assert!((p.x - q.x).abs() < 1e-6 && (p.y - q.y).abs() < 1e-6);
+
+        let collinear: Vec<(Point, Point)> = (0..4)
+            .map(|i| {
+                let v = i as f64;
+                (Point { x: v, y: v }, Point { x: v, y: v })
+            })
+            .collect();
+        assert_eq!(None, TouchCalibration::from_samples(&collinear));
+    }
+
+    #[test]
+    fn raw_samples_on_a_line_are_rejected() {
+        let truth = TouchCalibration::default();
+        let samples = |raw: &[(f64, f64)]| -> Vec<(Point, Point)> {
+            raw.iter()
+                .map(|&(x, y)| {
+                    let raw = Point { x, y };
+                    let target = truth.map(&raw, 1.0, 1.0);
+                    (raw, target)
+                })
+                .collect()
+        };
+
+        // Three targets on the diagonal, touched with a bit of noise
+        let collinear = samples(&[
+            (300.0, 3800.0),
+            (2101.0, 2049.0),
+            (3900.0, 302.0),
+            (302.0, 3798.0),
+            (3899.0, 300.0),
+        ]);
+        assert_eq!(None, TouchCalibration::from_samples(&collinear));
+
+        let same = samples(&[(2048.0, 2048.0); 5]);
+        assert_eq!(None, TouchCalibration::from_samples(&same));
+
+        let spread = samples(&[(300.0, 3800.0), (3900.0, 302.0), (350.0, 250.0)]);
+        let solved = TouchCalibration::from_samples(&spread).unwrap();
+        for (s, t) in solved.matrix.iter().zip(truth.matrix.iter()) {
+            assert!((s - t).abs() < 1e-9, "{:?} vs {:?}", solved, truth);
+        }
+    }
+}
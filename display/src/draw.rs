@@ -0,0 +1,76 @@
+use crate::{Point, Rect};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// Rectangle with corners rounded by `radius` (0 for sharp ones)
+fn rect_path(context: &cairo::Context, rect: &Rect, radius: f64) {
+    context.new_path();
+    let radius = radius.min(rect.width / 2.0).min(rect.height / 2.0);
+    if radius <= 0.0 {
+        context.rectangle(rect.x, rect.y, rect.width, rect.height);
+        return;
+    }
+
+    let (left, top) = (rect.x + radius, rect.y + radius);
+    let (right, bottom) = (rect.x + rect.width - radius, rect.y + rect.height - radius);
+    context.new_sub_path();
+    context.arc(right, top, radius, -FRAC_PI_2, 0.0);
+    context.arc(right, bottom, radius, 0.0, FRAC_PI_2);
+    context.arc(left, bottom, radius, FRAC_PI_2, PI);
+    context.arc(left, top, radius, PI, 3.0 * FRAC_PI_2);
+    context.close_path();
+}
+
+/// Fills everything (within the clip) with the current source
+pub(crate) fn clean(context: &cairo::Context) {
+    context.paint();
+}
+
+pub(crate) fn fill_rect(context: &cairo::Context, rect: &Rect, radius: f64) {
+    rect_path(context, rect, radius);
+    context.fill();
+}
+
+pub(crate) fn stroke_rect(context: &cairo::Context, rect: &Rect, radius: f64) {
+    rect_path(context, rect, radius);
+    context.stroke();
+}
+
+pub(crate) fn line(context: &cairo::Context, from: &Point, to: &Point) {
+    context.new_path();
+    context.move_to(from.x, from.y);
+    context.line_to(to.x, to.y);
+    context.stroke();
+}
+
+/// Pie slice between `start` and `end`
+pub(crate) fn fill_arc(
+    context: &cairo::Context,
+    center: &Point,
+    radius: f64,
+    start: f64,
+    end: f64,
+) {
+    context.new_path();
+    context.move_to(center.x, center.y);
+    context.arc(center.x, center.y, radius, start, end);
+    context.close_path();
+    context.fill();
+}
+
+pub(crate) fn stroke_arc(
+    context: &cairo::Context,
+    center: &Point,
+    radius: f64,
+    start: f64,
+    end: f64,
+) {
+    context.new_path();
+    context.arc(center.x, center.y, radius, start, end);
+    context.stroke();
+}
+
+pub(crate) fn clip(context: &cairo::Context, rect: &Rect) {
+    context.new_path();
+    context.rectangle(rect.x, rect.y, rect.width, rect.height);
+    context.clip();
+}
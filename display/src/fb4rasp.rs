@@ -1,13 +1,28 @@
-use crate::{input, Color, Display, Event, EventType, Point, TextSize};
+use crate::pixel::{Packing, PixelFormat};
+use crate::reader::{InputDevice, InputReader, ScreenMapping};
+use crate::{
+    draw,
+    fonts::{self, Fonts},
+    images::ImageCache,
+    input, Color, Display, Event, FontError, FontMetrics, Frame, ImageError, ImageStyle, Point,
+    Rect, Rotation, TextSize, TouchCalibration,
+};
 
 pub struct Fb4Rasp {
     fb: linuxfb::Framebuffer,
     mmap: memmap::MmapMut,
     original_content: Vec<u8>,
+    line_length: usize,
+    format: PixelFormat,
+    buffering: Buffering,
+    canvas: Option<Vec<u8>>,
+    rotation: Rotation,
+    images: ImageCache,
+    fonts: Fonts,
     cairo_ctx: Option<CairoCtx>,
     old_hw_cursor: Option<Vec<u8>>,
-    ev_devices: Option<Vec<evdev::Device>>,
-    touch_calibration: FbTouchCalibration,
+    ev_devices: Option<Vec<InputDevice>>,
+    touch_calibration: TouchCalibration,
 }
 
 #[derive(Debug)]
@@ -15,8 +30,16 @@ pub enum Error {
     FramebufferIssue,
 }
 
+/// How a composed frame becomes visible.
+enum Buffering {
+    /// Frame is copied to the (only) visible page in one pass on `finish()`
+    Copy,
+    /// Frame goes to the hidden page of the virtual framebuffer, which is
+    /// panned to on `finish()`
+    PageFlip { back_page: usize },
+}
+
 struct CairoCtx {
-    #[allow(dead_code)]
     surface: cairo::Surface,
     context: cairo::Context,
 }
@@ -35,6 +58,12 @@ impl std::fmt::Debug for Fb4Rasp {
 
 impl Drop for Fb4Rasp {
     fn drop(&mut self) {
+        self.cairo_ctx = None;
+        if let Buffering::PageFlip { .. } = self.buffering {
+            if self.fb.set_offset((0, 0)).is_err() {
+                log::warn!("Failure to restore framebuffer offset");
+            }
+        }
         self.mmap.copy_from_slice(&self.original_content);
         if self.old_hw_cursor.is_some() {
             use std::io::prelude::*;
@@ -53,11 +82,13 @@ impl Drop for Fb4Rasp {
 
 impl<'a> Display<'a> for Fb4Rasp {
     fn width(&self) -> usize {
-        self.fb.get_size().0 as usize
+        let (width, height) = self.device_size();
+        self.rotation.size(width, height).0
     }
 
     fn height(&self) -> usize {
-        self.fb.get_size().1 as usize
+        let (width, height) = self.device_size();
+        self.rotation.size(width, height).1
     }
 
     fn bytes_per_pixel(&self) -> usize {
@@ -65,40 +96,29 @@ impl<'a> Display<'a> for Fb4Rasp {
     }
 
     fn clean(&mut self) {
-        let bpp = self.bytes_per_pixel();
-        if bpp == 2 {
-            self.clean_int::<u16>();
-        } else {
-            self.clean_int::<u32>();
+        match self.cairo_ctx.as_ref() {
+            Some(ctx) => draw::clean(&ctx.context),
+            None => self.draw_frame().fill(0),
         }
     }
 
     fn start(&mut self) {
-        let width = self.width() as i32;
-        let height = self.height() as i32;
-        let bpp = self.bytes_per_pixel();
+        let (width, height) = self.device_size();
+        let (width, height) = (width as i32, height as i32);
+        let color_format = self.format.cairo_format();
+        let stride = self.canvas_stride() as i32;
         // Retrieve a slice for the current backbuffer:
-        let frame: &mut [u8] = &mut self.mmap[..];
-
-        let surface = unsafe {
-            let color_format = if bpp == 2 {
-                4 /*CAIRO_FORMAT_RGB16_565*/
-            } else {
-                0 /*CAIRO_FORMAT_ARGB32*/
-            };
-            let stride = cairo_sys::cairo_format_stride_for_width(color_format, width);
-            // log::debug!("Used stride for cairo: {}", stride);
-            cairo::Surface::from_raw_none(cairo_sys::cairo_image_surface_create_for_data(
-                frame.as_mut_ptr(),
-                color_format,
-                width,
-                height,
-                stride,
-            ))
-        };
-
-        let context = cairo::Context::new(&surface);
-        self.cairo_ctx = Some(CairoCtx { surface, context });
+        let frame = self.draw_frame();
+
+        match frame_surface(frame, color_format, width, height, stride) {
+            Ok(surface) => {
+                let context = cairo::Context::new(&surface);
+                self.rotation.apply(&context, width as f64, height as f64);
+                self.fonts.select(&context, fonts::DEFAULT_ROLE);
+                self.cairo_ctx = Some(CairoCtx { surface, context });
+            }
+            Err(e) => log::error!("Failed to create cairo surface: {}", e),
+        }
     }
 
     fn started(&self) -> bool {
@@ -116,11 +136,7 @@ impl<'a> Display<'a> for Fb4Rasp {
 
     fn text_size(&self, what: &str) -> TextSize {
         let context = &self.cairo_ctx.as_ref().unwrap().context;
-        let extents = context.text_extents(what);
-        TextSize {
-            width: extents.width,
-            height: extents.height,
-        }
+        self.fonts.text_size(context, what)
     }
 
     fn render_text(&mut self, r#where: &Point, what: &str) -> Option<TextSize> {
@@ -130,12 +146,9 @@ impl<'a> Display<'a> for Fb4Rasp {
 
         let context = &self.cairo_ctx.as_ref().unwrap().context;
         context.move_to(r#where.x, r#where.y);
-        let extents = context.text_extents(what);
-        context.show_text(what);
-        Some(TextSize {
-            width: extents.width,
-            height: extents.height,
-        })
+        let size = self.fonts.text_size(context, what);
+        self.fonts.show_text(context, what);
+        Some(size)
     }
 
     fn set_font(&mut self, name: &str) {
@@ -144,9 +157,7 @@ impl<'a> Display<'a> for Fb4Rasp {
         }
 
         let context = &self.cairo_ctx.as_ref().unwrap().context;
-        let font =
-            cairo::FontFace::toy_create(name, cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-        context.set_font_face(&font);
+        self.fonts.select(context, name);
     }
 
     fn set_font_size(&mut self, size: f64) {
@@ -158,8 +169,184 @@ impl<'a> Display<'a> for Fb4Rasp {
         context.set_font_size(size);
     }
 
+    fn font_metrics(&self) -> FontMetrics {
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        let extents = context.font_extents();
+        FontMetrics {
+            ascent: extents.ascent,
+            descent: extents.descent,
+            line_height: extents.height,
+        }
+    }
+
+    fn text_width(&self, what: &str) -> f64 {
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        self.fonts.text_width(context, what)
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        context.set_line_width(width);
+    }
+
+    fn set_dash(&mut self, dashes: &[f64], offset: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        context.set_dash(dashes, offset);
+    }
+
+    fn draw_line(&mut self, from: &Point, to: &Point) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::line(context, from, to);
+    }
+
+    fn fill_rect(&mut self, rect: &Rect, radius: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::fill_rect(context, rect, radius);
+    }
+
+    fn stroke_rect(&mut self, rect: &Rect, radius: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::stroke_rect(context, rect, radius);
+    }
+
+    fn fill_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::fill_arc(context, center, radius, start, end);
+    }
+
+    fn stroke_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::stroke_arc(context, center, radius, start, end);
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        draw::clip(context, rect);
+    }
+
+    fn reset_clip(&mut self) {
+        if !self.started() {
+            return;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        context.reset_clip();
+    }
+
+    fn load_image(&mut self, name: &str, path: &std::path::Path) -> Result<(), ImageError> {
+        self.images.load(name, path)
+    }
+
+    fn load_font(&mut self, role: &str, paths: &[std::path::PathBuf]) -> Result<(), FontError> {
+        self.fonts.load(role, paths)
+    }
+
+    fn image_size(&self, name: &str, style: &ImageStyle) -> Option<TextSize> {
+        self.images.size(name, style)
+    }
+
+    fn render_image(
+        &mut self,
+        r#where: &Point,
+        name: &str,
+        style: &ImageStyle,
+    ) -> Option<TextSize> {
+        if !self.started() {
+            return None;
+        }
+
+        let context = &self.cairo_ctx.as_ref().unwrap().context;
+        self.images.draw(context, name, r#where, style)
+    }
+
     fn finish(&mut self) {
-        self.cairo_ctx = None;
+        match self.cairo_ctx.take() {
+            Some(ctx) => ctx.surface.flush(),
+            None => return,
+        }
+
+        let (width, height) = self.device_size();
+        let frame_size = self.frame_size();
+        let canvas_stride = self.canvas_stride();
+        let start = self.target_page() * frame_size;
+        if let Some(canvas) = self.canvas.as_ref() {
+            let target = &mut self.mmap[start..start + frame_size];
+            match &self.format {
+                PixelFormat::Native(_) => target.copy_from_slice(canvas),
+                PixelFormat::Converted(packing) => packing.convert(
+                    canvas,
+                    canvas_stride,
+                    target,
+                    self.line_length,
+                    width,
+                    height,
+                ),
+            }
+        }
+
+        if let Buffering::PageFlip { back_page } = &mut self.buffering {
+            let y_offset = (*back_page * height) as u32;
+            if let Err(e) = self.fb.set_offset((0, y_offset)) {
+                log::warn!("Failed to flip framebuffer page: {:?}", e);
+            }
+            *back_page = 1 - *back_page;
+        }
+    }
+
+    fn frame(&self) -> Option<Frame> {
+        let (width, height) = self.device_size();
+        // Canvas keeps the last frame, otherwise it is on the visible page
+        let data = match self.canvas.as_ref() {
+            Some(canvas) => &canvas[..],
+            None => {
+                let frame_size = self.frame_size();
+                let start = match self.buffering {
+                    Buffering::Copy => 0,
+                    Buffering::PageFlip { back_page } => (1 - back_page) * frame_size,
+                };
+                &self.mmap[start..start + frame_size]
+            }
+        };
+        Frame::from_surface_data(
+            data,
+            self.format.cairo_format(),
+            self.canvas_stride(),
+            width,
+            height,
+            self.rotation,
+        )
     }
 
     fn init_events(&mut self) {
@@ -168,61 +355,20 @@ impl<'a> Display<'a> for Fb4Rasp {
             for device in devices.iter() {
                 log::debug!("Found input devices: {:?}", device);
             }
-            self.ev_devices = Some(devices);
+            self.ev_devices = Some(devices.into_iter().map(InputDevice::new).collect());
         }
     }
 
     fn get_events(&mut self) -> Vec<Event> {
-        struct TempPos {
-            x: Option<i32>,
-            y: Option<i32>,
-        }
-
-        let mut positions = vec![];
-        let calibration = self.touch_calibration;
+        let mapping = self.screen_mapping();
+        let mut events = vec![];
         if let Some(devices) = &mut self.ev_devices {
             for device in devices.iter_mut() {
-                let events = &mut device.events();
-                match events {
-                    Ok(raw_events) => {
-                        let mut pos = TempPos { x: None, y: None };
-                        for event in raw_events {
-                            let e: input::Event = event.into();
-                            log::debug!("Raw event: {:?}", &e);
-                            match e.r#type {
-                                input::EvType::Unknown(_) => log::debug!("Unknown event: {:?}", &e),
-                                input::EvType::Relative(_) => {
-                                    log::debug!("Not supported relative event: {:?}", &e)
-                                }
-                                input::EvType::Absolute(a) => match a {
-                                    input::Abs::ABS_X => pos.x = Some(e.value),
-                                    input::Abs::ABS_Y => pos.y = Some(e.value),
-                                    _ => (),
-                                },
-                            }
-
-                            if pos.x.is_some() && pos.y.is_some() {
-                                positions.push(Event {
-                                    what: EventType::Touched,
-                                    position: calibration.get_pos(&Point {
-                                        x: pos.x.unwrap() as f64,
-                                        y: pos.y.unwrap() as f64,
-                                    }),
-                                });
-
-                                pos.x = None;
-                                pos.y = None;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::debug!("error {:?} ", e);
-                    }
-                }
+                events.extend(device.read_events(&mapping));
             }
         }
 
-        positions
+        events
     }
 
     type DrawingBackend = plotters_cairo::CairoBackend<'a>;
@@ -235,47 +381,63 @@ impl<'a> Display<'a> for Fb4Rasp {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct FbTouchCalibration {
-    min_x: f64,
-    max_x: f64,
-    min_y: f64,
-    max_y: f64,
-    swap_axes: bool,
-
-    scale_x: f64,
-    scale_y: f64,
+/// Basic properties of a framebuffer device, as reported by the driver.
+#[derive(Debug)]
+pub struct FramebufferInfo {
+    pub path: std::path::PathBuf,
+    pub size: (u32, u32),
+    pub bytes_per_pixel: u32,
+    pub pixel_layout: String,
+    pub physical_size: (u32, u32),
 }
 
-impl FbTouchCalibration {
-    fn new(min_x: isize, max_x: isize, min_y: isize, max_y: isize, swap_axes: bool) -> Self {
-        Self {
-            min_x: min_x as f64,
-            max_x: max_x as f64,
-            min_y: min_y as f64,
-            max_y: max_y as f64,
-            swap_axes,
-            scale_x: 320.0 / (max_x - min_x) as f64,
-            scale_y: 480.0 / (max_y - min_y) as f64,
-        }
-    }
-
-    fn get_pos(&self, pos: &Point) -> Point {
-        let x = (pos.x - self.min_x) * self.scale_x;
-        let y = (pos.y - self.min_y) * self.scale_y;
-        if self.swap_axes {
-            Point { x: y, y: x }
-        } else {
-            Point { x, y }
-        }
+impl std::fmt::Display for FramebufferInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}x{} px, {} bytes per pixel, {}x{} mm\n    pixel layout: {}",
+            self.path.display(),
+            self.size.0,
+            self.size.1,
+            self.bytes_per_pixel,
+            self.physical_size.0,
+            self.physical_size.1,
+            self.pixel_layout
+        )
     }
 }
 
 impl Fb4Rasp {
-    pub fn new() -> Result<Self, Error> {
-        // Instead of hardcoding the path, you could also use `Framebuffer::list()`
-        // to find paths to available devices.
-        let fb = linuxfb::Framebuffer::new("/dev/fb0")?;
+    pub fn list() -> Vec<FramebufferInfo> {
+        let paths = match linuxfb::Framebuffer::list() {
+            Ok(p) => p,
+            Err(e) => {
+                log::error!("Failed to enumerate framebuffer devices: {}", e);
+                return vec![];
+            }
+        };
+
+        paths
+            .into_iter()
+            .filter_map(|path| match linuxfb::Framebuffer::new(&path) {
+                Ok(fb) => Some(FramebufferInfo {
+                    size: fb.get_size(),
+                    bytes_per_pixel: fb.get_bytes_per_pixel(),
+                    pixel_layout: format!("{:?}", fb.get_pixel_layout()),
+                    physical_size: fb.get_physical_size(),
+                    path,
+                }),
+                Err(e) => {
+                    log::warn!("Failed to open {:?}: {:?}", &path, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        log::debug!("Opening framebuffer {:?}", path.as_ref());
+        let fb = linuxfb::Framebuffer::new(path.as_ref())?;
 
         log::debug!("Size in pixels: {:?}", fb.get_size());
         log::debug!("Bytes per pixel: {:?}", fb.get_bytes_per_pixel());
@@ -290,6 +452,39 @@ impl Fb4Rasp {
         let mmap = fb.map()?;
         let original_content = mmap.to_vec();
 
+        let bytes_per_pixel = fb.get_bytes_per_pixel() as usize;
+        let line_length = Self::get_line_length(path.as_ref(), width as usize * bytes_per_pixel);
+        let format = PixelFormat::new(Packing::new(&fb.get_pixel_layout(), bytes_per_pixel));
+        log::debug!("Line length: {}, format: {:?}", line_length, format);
+
+        let frame_size = line_length * height as usize;
+        let virtual_height = fb.get_virtual_size().1 as i32;
+        let buffering = if virtual_height >= 2 * height && mmap.len() >= 2 * frame_size {
+            let visible_page = fb.get_offset().1 as i32 / height;
+            log::debug!(
+                "Using page flipping, virtual height: {}, visible page: {}",
+                virtual_height,
+                visible_page
+            );
+            Buffering::PageFlip {
+                back_page: if visible_page == 0 { 1 } else { 0 },
+            }
+        } else {
+            Buffering::Copy
+        };
+
+        // Memory cairo draws into, unless it can use the hidden page directly
+        let argb_size = Self::argb_stride(width as usize) * height as usize;
+        let canvas = match (&buffering, &format) {
+            (Buffering::PageFlip { .. }, PixelFormat::Native(_)) => None,
+            (_, PixelFormat::Native(_)) => Some(vec![0u8; frame_size]),
+            (_, PixelFormat::Converted(_)) => Some(vec![0u8; argb_size]),
+        };
+        log::debug!(
+            "Using offscreen canvas of {} bytes",
+            canvas.as_ref().map_or(0, |c| c.len())
+        );
+
         let mut old_hw_cursor: Option<Vec<u8>> = None;
         {
             use std::io::prelude::*;
@@ -323,46 +518,203 @@ impl Fb4Rasp {
             fb,
             mmap,
             original_content,
+            line_length,
+            format,
+            buffering,
+            canvas,
+            rotation: Rotation::Rotate0,
+            images: ImageCache::default(),
+            fonts: Fonts::default(),
             cairo_ctx: None,
             old_hw_cursor,
             ev_devices: None,
-            touch_calibration: FbTouchCalibration::new(238, 3996, 3931, 173, true),
+            touch_calibration: TouchCalibration::default(),
         })
     }
 
-    fn clean_int<T: std::convert::From<u8>>(&mut self) {
-        // Retrieve a slice for the current backbuffer:
-        let frame: &mut [u8] = &mut self.mmap[..];
-
-        // Writing byte-wise is neither very efficient, nor convenient.
-        // To write whole pixels, we can cast our buffer to the right format:
-        let (prefix, pixels, suffix) = unsafe { frame.align_to_mut::<T>() };
-
-        // Since we are using a type that can hold a whole pixel, it should
-        // always align nicely.
-        // Thus there is no prefix or suffix here:
-        assert_eq!(prefix.len(), 0);
-        assert_eq!(suffix.len(), 0);
-
-        // Now we can start filling the pixels:
-        let width = self.fb.get_size().0 as usize;
-        let height = self.fb.get_size().1 as usize;
-        for y in 0..height {
-            for x in 0..width {
-                pixels[x + y * width] = T::from(0);
-            }
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    pub fn set_touch_calibration(&mut self, calibration: TouchCalibration) {
+        self.touch_calibration = calibration;
+    }
+
+    /// Hands the input devices over to readers driven by tokio tasks, after
+    /// that `get_events` doesn't report anything. Must be called from within
+    /// a tokio runtime.
+    pub fn take_input_readers(&mut self) -> Vec<InputReader> {
+        if self.ev_devices.is_none() {
+            self.init_events();
         }
+
+        let mapping = self.screen_mapping();
+        self.ev_devices
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|device| match InputReader::new(device, mapping) {
+                Ok(reader) => Some(reader),
+                Err(e) => {
+                    log::error!("Failed to poll input device: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns uncalibrated positions of the first contact read since the
+    /// last call.
+    pub fn get_raw_touches(&mut self) -> Vec<Point> {
+        self.read_touch_samples()
+            .into_iter()
+            .filter(|s| s.slot == 0 && s.contact != input::Contact::Up)
+            .map(|s| s.position)
+            .collect()
     }
 }
 
 impl Fb4Rasp {
     /** PRIVATE PART **/
 
+    fn read_touch_samples(&mut self) -> Vec<input::TouchSample> {
+        let mut samples = vec![];
+        if let Some(devices) = &mut self.ev_devices {
+            for device in devices.iter_mut() {
+                samples.extend(device.read_samples());
+            }
+        }
+
+        samples
+    }
+
+    fn screen_mapping(&self) -> ScreenMapping {
+        let (width, height) = self.device_size();
+        ScreenMapping {
+            calibration: self.touch_calibration,
+            rotation: self.rotation,
+            width: width as f64,
+            height: height as f64,
+        }
+    }
+
     fn get_hw_cursor_filename() -> &'static str {
         "/sys/class/graphics/fbcon/cursor_blink"
     }
 
+    /// Reads the line length reported by the driver, which might include
+    /// padding, falls back to `default` when it is not available.
+    fn get_line_length(path: &std::path::Path, default: usize) -> usize {
+        let name = path
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_owned()));
+        if let Some(name) = name {
+            let filename = std::path::Path::new("/sys/class/graphics")
+                .join(name)
+                .join("stride");
+            match std::fs::read_to_string(&filename) {
+                Ok(content) => match content.trim().parse::<usize>() {
+                    Ok(stride) if stride >= default => return stride,
+                    _ => log::warn!("Invalid line length in {:?}: {}", &filename, &content),
+                },
+                Err(e) => log::info!("Failure to read {:?}: {}", &filename, e),
+            }
+        }
+
+        default
+    }
+
+    fn argb_stride(width: usize) -> usize {
+        let stride = unsafe {
+            cairo_sys::cairo_format_stride_for_width(cairo_sys::FORMAT_A_RGB32, width as i32)
+        };
+        stride as usize
+    }
+
+    fn device_size(&self) -> (usize, usize) {
+        let (width, height) = self.fb.get_size();
+        (width as usize, height as usize)
+    }
+
+    fn frame_size(&self) -> usize {
+        self.line_length * self.device_size().1
+    }
+
+    fn canvas_stride(&self) -> usize {
+        match self.format {
+            PixelFormat::Native(_) => self.line_length,
+            PixelFormat::Converted(_) => Self::argb_stride(self.device_size().0),
+        }
+    }
+
+    /// Page of the framebuffer the next frame is written to.
+    fn target_page(&self) -> usize {
+        match self.buffering {
+            Buffering::Copy => 0,
+            Buffering::PageFlip { back_page } => back_page,
+        }
+    }
+
+    /// Memory the next frame is composed in, never the visible one.
+    fn draw_frame(&mut self) -> &mut [u8] {
+        let frame_size = self.frame_size();
+        let start = self.target_page() * frame_size;
+        match self.canvas.as_mut() {
+            Some(canvas) => &mut canvas[..],
+            None => &mut self.mmap[start..start + frame_size],
+        }
+    }
+
     // fn is_inside(&self, pt: &Point) -> bool {
     //     pt.x < self.width() as f64 && pt.y < self.height() as f64
     // }
 }
+
+/// Surface drawing directly into `frame`, which has to outlive it
+fn frame_surface(
+    frame: &mut [u8],
+    format: cairo_sys::cairo_format_t,
+    width: i32,
+    height: i32,
+    stride: i32,
+) -> Result<cairo::Surface, cairo::Error> {
+    unsafe {
+        cairo::Surface::from_raw_full(cairo_sys::cairo_image_surface_create_for_data(
+            frame.as_mut_ptr(),
+            format,
+            width,
+            height,
+            stride,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_uses_background_color() {
+        let (width, height, stride): (usize, usize, usize) = (4, 2, 8);
+        let mut frame = vec![0u8; stride * height];
+        {
+            let surface = frame_surface(
+                &mut frame,
+                cairo_sys::FORMAT_RGB16_565,
+                width as i32,
+                height as i32,
+                stride as i32,
+            )
+            .unwrap();
+            let context = cairo::Context::new(&surface);
+            context.set_source_rgb(1.0, 0.0, 0.0);
+            draw::clean(&context);
+            surface.flush();
+        }
+
+        assert!(frame
+            .chunks_exact(2)
+            .all(|p| u16::from_ne_bytes([p[0], p[1]]) == 0xf800));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::TextSize;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub enum FontError {
+    FreeType(String),
+    Cairo(String),
+    /// Role without any font file
+    NoFonts,
+}
+
+impl From<cairo::Error> for FontError {
+    fn from(err: cairo::Error) -> Self {
+        FontError::Cairo(format!("{}", err))
+    }
+}
+
+// Not wrapped by cairo-rs, although it is built with FreeType support
+extern "C" {
+    fn cairo_ft_font_face_create_for_ft_face(
+        face: freetype_sys::FT_Face,
+        load_flags: std::os::raw::c_int,
+    ) -> *mut cairo_sys::cairo_font_face_t;
+}
+
+/// Role of text until another font is set
+pub(crate) const DEFAULT_ROLE: &str = "regular";
+
+/// Families used for roles which aren't configured, found by fontconfig
+const SYSTEM_ROLES: &[(&str, &str, cairo::FontWeight)] = &[
+    ("regular", "sans-serif", cairo::FontWeight::Normal),
+    ("bold", "sans-serif", cairo::FontWeight::Bold),
+    ("mono", "DejaVuSansMono", cairo::FontWeight::Normal),
+    ("icon", "sans-serif", cairo::FontWeight::Normal),
+];
+
+struct Library(freetype_sys::FT_Library);
+
+// Only used while holding the lock
+unsafe impl Send for Library {}
+
+/// Shared by all faces, FreeType requires creating and destroying them to be
+/// serialized. It is never freed as cairo may keep faces in its caches.
+static LIBRARY: Mutex<Option<Library>> = Mutex::new(None);
+
+/// FreeType face owned by the cairo face created for it
+struct FtFace(freetype_sys::FT_Face);
+
+impl Drop for FtFace {
+    fn drop(&mut self) {
+        let _library = LIBRARY.lock();
+        unsafe {
+            freetype_sys::FT_Done_Face(self.0);
+        }
+    }
+}
+
+static FT_FACE_KEY: cairo::UserDataKey<FtFace> = cairo::UserDataKey::new();
+
+struct Face {
+    /// Valid as long as `cairo` is
+    ft: freetype_sys::FT_Face,
+    cairo: cairo::FontFace,
+}
+
+impl Face {
+    fn load(path: &Path) -> Result<Self, FontError> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| FontError::FreeType(format!("Invalid path {:?}", path)))?;
+
+        let mut ft = std::ptr::null_mut();
+        let error = {
+            let mut library = LIBRARY.lock().unwrap();
+            if library.is_none() {
+                let mut raw = std::ptr::null_mut();
+                let error = unsafe { freetype_sys::FT_Init_FreeType(&mut raw) };
+                if error != 0 {
+                    return Err(FontError::FreeType(format!(
+                        "Failed to initialize FreeType: error {}",
+                        error
+                    )));
+                }
+                *library = Some(Library(raw));
+            }
+            let library = library.as_ref().unwrap();
+            unsafe { freetype_sys::FT_New_Face(library.0, c_path.as_ptr(), 0, &mut ft) }
+        };
+        if error != 0 {
+            return Err(FontError::FreeType(format!(
+                "Failed to load font {:?}: error {}",
+                path, error
+            )));
+        }
+
+        let owned = Rc::new(FtFace(ft));
+        let raw = unsafe { cairo_ft_font_face_create_for_ft_face(ft, 0) };
+        let status = unsafe { cairo_sys::cairo_font_face_status(raw) };
+        let cairo = unsafe { cairo::FontFace::from_raw_full(raw) };
+        if status != cairo_sys::STATUS_SUCCESS {
+            return Err(FontError::Cairo(format!(
+                "Failed to create font face for {:?}: status {}",
+                path, status
+            )));
+        }
+        cairo.set_user_data(&FT_FACE_KEY, owned);
+
+        Ok(Self { ft, cairo })
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        unsafe { freetype_sys::FT_Get_Char_Index(self.ft, c as freetype_sys::FT_ULong) != 0 }
+    }
+}
+
+/// Font files of a role, glyphs missing in one are taken from the next one.
+struct FontChain {
+    faces: Vec<Face>,
+}
+
+/// Fonts loaded by role, each backend keeps its own like images.
+#[derive(Default)]
+pub(crate) struct Fonts {
+    roles: HashMap<String, FontChain>,
+    /// Role set last, none if a font of the system is used
+    current: Option<String>,
+}
+
+impl Fonts {
+    /// Loads font files of a role, replacing any fonts it had.
+    pub fn load(&mut self, role: &str, paths: &[PathBuf]) -> Result<(), FontError> {
+        if paths.is_empty() {
+            return Err(FontError::NoFonts);
+        }
+
+        let faces = paths
+            .iter()
+            .map(|p| Face::load(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.roles.insert(role.to_owned(), FontChain { faces });
+        Ok(())
+    }
+
+    /// Sets the font of a loaded role, or a font family of the system
+    pub fn select(&mut self, context: &cairo::Context, name: &str) {
+        if let Some(chain) = self.roles.get(name) {
+            context.set_font_face(&chain.faces[0].cairo);
+            self.current = Some(name.to_owned());
+            return;
+        }
+
+        self.current = None;
+        let (family, weight) = SYSTEM_ROLES
+            .iter()
+            .find(|(role, _, _)| *role == name)
+            .map_or((name, cairo::FontWeight::Normal), |(_, family, weight)| {
+                (*family, *weight)
+            });
+        let font = cairo::FontFace::toy_create(family, cairo::FontSlant::Normal, weight);
+        context.set_font_face(&font);
+    }
+
+    /// Calls `f` for parts of `what` which are drawn with the same font, with
+    /// that font set. The first font of the chain is set again afterwards.
+    fn for_runs<T, F>(&self, context: &cairo::Context, what: &str, mut f: F) -> Vec<T>
+    where
+        F: FnMut(&cairo::Context, &str) -> T,
+    {
+        let chain = match self.current.as_ref().and_then(|c| self.roles.get(c)) {
+            Some(chain) if chain.faces.len() > 1 => chain,
+            _ => return vec![f(context, what)],
+        };
+
+        let runs = split_runs(what, chain.faces.len(), |index, c| {
+            chain.faces[index].has_glyph(c)
+        });
+
+        let results = runs
+            .into_iter()
+            .map(|(index, text)| {
+                context.set_font_face(&chain.faces[index].cairo);
+                f(context, text)
+            })
+            .collect();
+        context.set_font_face(&chain.faces[0].cairo);
+        results
+    }
+
+    /// Size of the inked area, like `text_extents` but for all faces of the
+    /// chain
+    pub fn text_size(&self, context: &cairo::Context, what: &str) -> TextSize {
+        ink_size(&self.for_runs(context, what, |context, text| context.text_extents(text)))
+    }
+
+    pub fn text_width(&self, context: &cairo::Context, what: &str) -> f64 {
+        self.for_runs(context, what, |context, text| {
+            context.text_extents(text).x_advance
+        })
+        .iter()
+        .sum()
+    }
+
+    /// Shows text at the current point, which is moved after it
+    pub fn show_text(&self, context: &cairo::Context, what: &str) {
+        self.for_runs(context, what, |context, text| context.show_text(text));
+    }
+}
+
+/// Bounding box of the ink of runs drawn one after another, runs without any
+/// (e.g. spaces) only move the following ones.
+fn ink_size(runs: &[cairo::TextExtents]) -> TextSize {
+    let mut x = 0.0;
+    let mut bounds: Option<(f64, f64, f64, f64)> = None;
+    for run in runs {
+        if run.width > 0.0 || run.height > 0.0 {
+            let (left, top) = (x + run.x_bearing, run.y_bearing);
+            let (right, bottom) = (left + run.width, top + run.height);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                None => (left, top, right, bottom),
+            });
+        }
+        x += run.x_advance;
+    }
+
+    let (left, top, right, bottom) = bounds.unwrap_or_default();
+    TextSize {
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+/// Splits `what` into parts drawn with the same face of a chain of `faces`,
+/// the first one with a glyph for a character is used. Characters which no
+/// face has are drawn with the first one.
+fn split_runs<F>(what: &str, faces: usize, has_glyph: F) -> Vec<(usize, &str)>
+where
+    F: Fn(usize, char) -> bool,
+{
+    let face_of = |c: char| (0..faces).find(|&i| has_glyph(i, c)).unwrap_or(0);
+    let mut runs: Vec<(usize, &str)> = Vec::new();
+    let mut start = 0;
+    let mut face = None;
+    for (i, c) in what.char_indices() {
+        let index = face_of(c);
+        match face {
+            Some(f) if f != index => {
+                runs.push((f, &what[start..i]));
+                start = i;
+            }
+            _ => {}
+        }
+        face = Some(index);
+    }
+    if let Some(f) = face {
+        runs.push((f, &what[start..]));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_glyphs_are_taken_from_next_faces() {
+        // First face has ASCII only, the second one also the degree sign
+        let has_glyph = |index: usize, c: char| c.is_ascii() || (index == 1 && c == '\u{b0}');
+
+        assert_eq!(
+            vec![(0, "21.5"), (1, "\u{b0}"), (0, "C")],
+            split_runs("21.5\u{b0}C", 2, has_glyph)
+        );
+        assert_eq!(
+            vec![(1, "\u{b0}\u{b0}")],
+            split_runs("\u{b0}\u{b0}", 2, has_glyph)
+        );
+        // Characters missing in all faces use the first one
+        assert_eq!(
+            vec![(0, "\u{2603}\u{2602}")],
+            split_runs("\u{2603}\u{2602}", 2, has_glyph)
+        );
+        assert_eq!(
+            vec![(0, "a\u{2603}"), (1, "\u{b0}")],
+            split_runs("a\u{2603}\u{b0}", 2, has_glyph)
+        );
+        assert!(split_runs("", 2, has_glyph).is_empty());
+    }
+
+    fn extents(x_bearing: f64, y_bearing: f64, width: f64, height: f64) -> cairo::TextExtents {
+        cairo::TextExtents {
+            x_bearing,
+            y_bearing,
+            width,
+            height,
+            x_advance: x_bearing + width + 1.0,
+            y_advance: 0.0,
+        }
+    }
+
+    #[test]
+    fn ink_of_runs_is_merged() {
+        let single = ink_size(&[extents(1.0, -10.0, 20.0, 12.0)]);
+        assert_eq!((20.0, 12.0), (single.width, single.height));
+
+        // Second run starts at 22, a taller one lower
+        let runs = ink_size(&[
+            extents(1.0, -10.0, 20.0, 12.0),
+            extents(2.0, -12.0, 5.0, 8.0),
+        ]);
+        assert_eq!((28.0, 14.0), (runs.width, runs.height));
+
+        // A trailing space only advances
+        let space = ink_size(&[extents(1.0, -10.0, 20.0, 12.0), extents(0.0, 0.0, 0.0, 0.0)]);
+        assert_eq!((20.0, 12.0), (space.width, space.height));
+        let empty = ink_size(&[]);
+        assert_eq!((0.0, 0.0), (empty.width, empty.height));
+    }
+}
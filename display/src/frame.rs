@@ -0,0 +1,102 @@
+use crate::{CairoImageError, Rotation};
+
+/// Finished frame as seen by the drawing code, i.e. rotated like it, with
+/// `0x00RRGGBB` pixels row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Frame {
+    /// Converts pixels of a cairo surface, `width` and `height` are the size
+    /// of the device.
+    pub(crate) fn from_surface_data(
+        data: &[u8],
+        format: cairo_sys::cairo_format_t,
+        stride: usize,
+        width: usize,
+        height: usize,
+        rotation: Rotation,
+    ) -> Option<Self> {
+        let pixel: fn(&[u8], usize) -> u32 = match format {
+            // Premultiplied, i.e. composed over black
+            cairo_sys::FORMAT_A_RGB32 | cairo_sys::FORMAT_RGB24 => |data, offset| {
+                u32::from_ne_bytes([
+                    data[offset],
+                    data[offset + 1],
+                    data[offset + 2],
+                    data[offset + 3],
+                ]) & 0xff_ff_ff
+            },
+            cairo_sys::FORMAT_RGB16_565 => |data, offset| {
+                let rgb = u16::from_ne_bytes([data[offset], data[offset + 1]]) as u32;
+                let (r, g, b) = ((rgb >> 11) & 0x1f, (rgb >> 5) & 0x3f, rgb & 0x1f);
+                ((r << 3 | r >> 2) << 16) | ((g << 2 | g >> 4) << 8) | (b << 3 | b >> 2)
+            },
+            _ => return None,
+        };
+        let bytes_per_pixel = if format == cairo_sys::FORMAT_RGB16_565 {
+            2
+        } else {
+            4
+        };
+
+        let (frame_width, frame_height) = rotation.size(width, height);
+        let mut pixels = Vec::with_capacity(frame_width * frame_height);
+        for y in 0..frame_height {
+            for x in 0..frame_width {
+                let (dx, dy) = rotation.to_device_pixel(x, y, width, height);
+                pixels.push(pixel(data, dy * stride + dx * bytes_per_pixel));
+            }
+        }
+
+        Some(Self {
+            width: frame_width,
+            height: frame_height,
+            pixels,
+        })
+    }
+
+    /// Decodes a PNG image, e.g. one written by `write_png`
+    pub fn read_png<R: std::io::Read>(stream: &mut R) -> Result<Self, CairoImageError> {
+        let mut surface = cairo::ImageSurface::create_from_png(stream)?;
+        let (width, height) = (surface.get_width() as usize, surface.get_height() as usize);
+        let format = match surface.get_format() {
+            cairo::Format::Rgb24 => cairo_sys::FORMAT_RGB24,
+            cairo::Format::ARgb32 => cairo_sys::FORMAT_A_RGB32,
+            f => {
+                return Err(CairoImageError::Cairo(format!(
+                    "Unsupported PNG format {:?}",
+                    f
+                )))
+            }
+        };
+        let stride = surface.get_stride() as usize;
+        let data = surface.get_data()?;
+        Self::from_surface_data(&data, format, stride, width, height, Rotation::Rotate0)
+            .ok_or_else(|| CairoImageError::Cairo(format!("Unsupported PNG format {:?}", format)))
+    }
+
+    /// Encodes the frame as PNG, e.g. for screenshots
+    pub fn write_png<W: std::io::Write>(&self, stream: &mut W) -> Result<(), CairoImageError> {
+        let mut surface = cairo::ImageSurface::create(
+            cairo::Format::Rgb24,
+            self.width as i32,
+            self.height as i32,
+        )?;
+        let stride = surface.get_stride() as usize;
+        if self.width > 0 {
+            let mut data = surface.get_data()?;
+            for (y, row) in self.pixels.chunks_exact(self.width).enumerate() {
+                for (x, pixel) in row.iter().enumerate() {
+                    let offset = y * stride + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&pixel.to_ne_bytes());
+                }
+            }
+        }
+        surface.write_to_png(stream)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{
+    input::{Contact, TouchSample},
+    Event, EventType, Point,
+};
+use std::collections::BTreeMap;
+
+/// Touch held at least this long without moving is a long press
+const LONG_PRESS_USECONDS: i64 = 700_000;
+/// Drag released within this time is a swipe
+const SWIPE_MAX_USECONDS: i64 = 500_000;
+/// Movement (in pixels) needed before a touch becomes a drag
+const DRAG_THRESHOLD: f64 = 10.0;
+/// Minimal length (in pixels) of a swipe
+const SWIPE_MIN_DISTANCE: f64 = 50.0;
+
+#[derive(Debug)]
+struct Press {
+    slot: usize,
+    start: Point,
+    start_useconds: i64,
+    last: Point,
+    dragging: bool,
+    long_press: bool,
+}
+
+/// Turns touch samples (already in screen coordinates) into gestures.
+///
+/// Single finger gestures follow the first contact and are cancelled once a
+/// second finger touches the screen, which starts a pinch instead.
+#[derive(Debug, Default)]
+pub(crate) struct GestureRecognizer {
+    contacts: BTreeMap<usize, Point>,
+    press: Option<Press>,
+    /// Finger distance of the last pinch event
+    pinch: Option<f64>,
+}
+
+impl GestureRecognizer {
+    pub fn process(&mut self, sample: &TouchSample) -> Vec<Event> {
+        let position = &sample.position;
+        let mut events = vec![Event {
+            what: match sample.contact {
+                Contact::Down => EventType::ContactDown(sample.slot),
+                Contact::Move => EventType::ContactMove(sample.slot),
+                Contact::Up => EventType::ContactUp(sample.slot),
+            },
+            position: position.clone(),
+        }];
+
+        match sample.contact {
+            Contact::Down => {
+                self.contacts.insert(sample.slot, position.clone());
+                if self.contacts.len() == 1 {
+                    self.press = Some(Press {
+                        slot: sample.slot,
+                        start: position.clone(),
+                        start_useconds: sample.useconds,
+                        last: position.clone(),
+                        dragging: false,
+                        long_press: false,
+                    });
+                    events.push(Event {
+                        what: EventType::Touched,
+                        position: position.clone(),
+                    });
+                } else {
+                    self.press = None;
+                    self.pinch = self.fingers().map(|(a, b)| distance(&a, &b));
+                }
+            }
+            Contact::Move => {
+                if let Some(p) = self.contacts.get_mut(&sample.slot) {
+                    *p = position.clone();
+                }
+                events.extend(self.poll(sample.useconds));
+                events.extend(self.drag(sample.slot, position));
+                events.extend(self.pinch());
+            }
+            Contact::Up => {
+                self.contacts.remove(&sample.slot);
+                if self.contacts.len() < 2 {
+                    self.pinch = None;
+                }
+                events.extend(self.release(sample));
+            }
+        }
+
+        events
+    }
+
+    /// Reports a long press while the touch is still held, `useconds` uses
+    /// the clock of the input events.
+    pub fn poll(&mut self, useconds: i64) -> Option<Event> {
+        let press = self.press.as_mut()?;
+        if press.dragging
+            || press.long_press
+            || useconds - press.start_useconds < LONG_PRESS_USECONDS
+        {
+            return None;
+        }
+
+        press.long_press = true;
+        Some(Event {
+            what: EventType::LongPress,
+            position: press.last.clone(),
+        })
+    }
+
+    /// Whether `poll` could still report a long press
+    pub fn long_press_pending(&self) -> bool {
+        matches!(&self.press, Some(p) if !p.dragging && !p.long_press)
+    }
+
+    /// The first two contacts
+    fn fingers(&self) -> Option<(Point, Point)> {
+        let mut contacts = self.contacts.values();
+        Some((contacts.next()?.clone(), contacts.next()?.clone()))
+    }
+
+    fn drag(&mut self, slot: usize, position: &Point) -> Option<Event> {
+        let press = self.press.as_mut().filter(|p| p.slot == slot)?;
+        if !press.dragging && distance(&press.start, position) > DRAG_THRESHOLD {
+            press.dragging = true;
+        }
+
+        let (dx, dy) = (position.x - press.last.x, position.y - press.last.y);
+        press.last = position.clone();
+        if press.dragging {
+            Some(Event {
+                what: EventType::Drag { dx, dy },
+                position: position.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn pinch(&mut self) -> Option<Event> {
+        let last = self.pinch?;
+        let (a, b) = self.fingers()?;
+        let current = distance(&a, &b);
+        if last <= 0.0 || current == last {
+            return None;
+        }
+
+        self.pinch = Some(current);
+        Some(Event {
+            what: EventType::Pinch {
+                scale: current / last,
+            },
+            position: Point {
+                x: (a.x + b.x) / 2.0,
+                y: (a.y + b.y) / 2.0,
+            },
+        })
+    }
+
+    fn release(&mut self, sample: &TouchSample) -> Option<Event> {
+        let press = self.press.take()?;
+        if press.slot != sample.slot {
+            self.press = Some(press);
+            return None;
+        }
+
+        let duration = sample.useconds - press.start_useconds;
+        let what = if press.dragging {
+            if duration <= SWIPE_MAX_USECONDS {
+                swipe(&press.start, &press.last)
+            } else {
+                None
+            }
+        } else if press.long_press {
+            None
+        } else if duration >= LONG_PRESS_USECONDS {
+            Some(EventType::LongPress)
+        } else {
+            Some(EventType::Tap)
+        };
+
+        what.map(|what| Event {
+            what,
+            position: press.last,
+        })
+    }
+}
+
+fn distance(a: &Point, b: &Point) -> f64 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+fn swipe(start: &Point, end: &Point) -> Option<EventType> {
+    if distance(start, end) < SWIPE_MIN_DISTANCE {
+        return None;
+    }
+
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    Some(if dx.abs() >= dy.abs() {
+        if dx > 0.0 {
+            EventType::SwipeRight
+        } else {
+            EventType::SwipeLeft
+        }
+    } else if dy > 0.0 {
+        EventType::SwipeDown
+    } else {
+        EventType::SwipeUp
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gestures(samples: &[(i64, Contact, f64, f64)]) -> Vec<EventType> {
+        let mut recognizer = GestureRecognizer::default();
+        samples
+            .iter()
+            .flat_map(|&(useconds, contact, x, y)| {
+                recognizer.process(&TouchSample {
+                    useconds,
+                    slot: 0,
+                    contact,
+                    position: Point { x, y },
+                })
+            })
+            .map(|e| e.what)
+            .filter(|what| {
+                !matches!(
+                    what,
+                    EventType::ContactDown(_) | EventType::ContactMove(_) | EventType::ContactUp(_)
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gestures_are_recognized() {
+        use Contact::*;
+
+        assert_eq!(
+            vec![EventType::Touched, EventType::Tap],
+            gestures(&[(0, Down, 10.0, 10.0), (100_000, Up, 12.0, 11.0)])
+        );
+        assert_eq!(
+            vec![EventType::Touched, EventType::LongPress],
+            gestures(&[(0, Down, 10.0, 10.0), (900_000, Up, 10.0, 10.0)])
+        );
+        assert_eq!(
+            vec![
+                EventType::Touched,
+                EventType::Drag { dx: 40.0, dy: 5.0 },
+                EventType::Drag { dx: 60.0, dy: 0.0 },
+                EventType::SwipeRight
+            ],
+            gestures(&[
+                (0, Down, 10.0, 10.0),
+                (50_000, Move, 50.0, 15.0),
+                (100_000, Move, 110.0, 15.0),
+                (150_000, Up, 110.0, 15.0)
+            ])
+        );
+        assert_eq!(
+            vec![
+                EventType::Touched,
+                EventType::Drag { dx: 0.0, dy: -80.0 },
+                EventType::SwipeUp
+            ],
+            gestures(&[
+                (0, Down, 100.0, 100.0),
+                (50_000, Move, 100.0, 20.0),
+                (100_000, Up, 100.0, 20.0)
+            ])
+        );
+        // Slow drag is not a swipe
+        assert_eq!(
+            vec![EventType::Touched, EventType::Drag { dx: -80.0, dy: 0.0 }],
+            gestures(&[
+                (0, Down, 100.0, 100.0),
+                (50_000, Move, 20.0, 100.0),
+                (2_000_000, Up, 20.0, 100.0)
+            ])
+        );
+
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.process(&TouchSample {
+            useconds: 0,
+            slot: 0,
+            contact: Down,
+            position: Point { x: 1.0, y: 1.0 },
+        });
+        assert!(recognizer.poll(500_000).is_none());
+        assert_eq!(EventType::LongPress, recognizer.poll(800_000).unwrap().what);
+        assert!(recognizer.poll(900_000).is_none());
+        assert_eq!(
+            vec![EventType::ContactUp(0)],
+            recognizer
+                .process(&TouchSample {
+                    useconds: 1_000_000,
+                    slot: 0,
+                    contact: Up,
+                    position: Point { x: 1.0, y: 1.0 },
+                })
+                .into_iter()
+                .map(|e| e.what)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{Color, Point, TextSize};
+use std::collections::{hash_map::Entry, HashMap};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(String),
+    Png(String),
+    Svg(String),
+    Cairo(String),
+    UnknownFormat,
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(format!("{}", err))
+    }
+}
+
+impl From<cairo::IoError> for ImageError {
+    fn from(err: cairo::IoError) -> Self {
+        ImageError::Png(format!("{}", err))
+    }
+}
+
+impl From<usvg::Error> for ImageError {
+    fn from(err: usvg::Error) -> Self {
+        ImageError::Svg(format!("{}", err))
+    }
+}
+
+impl From<cairo::Error> for ImageError {
+    fn from(err: cairo::Error) -> Self {
+        ImageError::Cairo(format!("{}", err))
+    }
+}
+
+/// How an image is drawn, the size is in pixels and keeps the aspect ratio
+/// if only one dimension is given.
+#[derive(Debug, Clone)]
+pub struct ImageStyle {
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    /// Replaces colors of the image, only its alpha channel is used then
+    pub tint: Option<Color>,
+    pub alpha: f64,
+}
+
+impl Default for ImageStyle {
+    fn default() -> Self {
+        Self {
+            width: None,
+            height: None,
+            tint: None,
+            alpha: 1.0,
+        }
+    }
+}
+
+enum Source {
+    Png(cairo::ImageSurface),
+    /// Rasterized on demand for every size it is drawn with
+    Svg {
+        tree: usvg::Tree,
+        rendered: HashMap<(i32, i32), cairo::ImageSurface>,
+    },
+}
+
+impl Source {
+    fn size(&self) -> (f64, f64) {
+        match self {
+            Source::Png(surface) => (surface.get_width() as f64, surface.get_height() as f64),
+            Source::Svg { tree, .. } => {
+                let size = tree.svg_node().size;
+                (size.width(), size.height())
+            }
+        }
+    }
+
+    fn surface(&mut self, width: i32, height: i32) -> Result<&cairo::ImageSurface, ImageError> {
+        match self {
+            Source::Png(surface) => Ok(surface),
+            Source::Svg { tree, rendered } => match rendered.entry((width, height)) {
+                Entry::Occupied(e) => Ok(e.into_mut()),
+                Entry::Vacant(e) => Ok(e.insert(render_svg(tree, width, height)?)),
+            },
+        }
+    }
+}
+
+fn render_svg(
+    tree: &usvg::Tree,
+    width: i32,
+    height: i32,
+) -> Result<cairo::ImageSurface, ImageError> {
+    let mut pixmap = tiny_skia::Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| ImageError::Svg(format!("Invalid size {}x{}", width, height)))?;
+    resvg::render(
+        tree,
+        usvg::FitTo::Size(width as u32, height as u32),
+        tiny_skia::Transform::default(),
+        pixmap.as_mut(),
+    )
+    .ok_or_else(|| ImageError::Svg("Rendering failed".to_owned()))?;
+
+    // Both are premultiplied, but cairo keeps pixels as native endian ARGB
+    let stride = cairo::Format::ARgb32
+        .stride_for_width(width as u32)
+        .map_err(|_| ImageError::Svg(format!("Invalid width {}", width)))?
+        as usize;
+    let mut data = vec![0u8; stride * height as usize];
+    for (row, pixels) in pixmap.pixels().chunks(width as usize).enumerate() {
+        for (column, p) in pixels.iter().enumerate() {
+            let argb = (p.alpha() as u32) << 24
+                | (p.red() as u32) << 16
+                | (p.green() as u32) << 8
+                | p.blue() as u32;
+            let offset = row * stride + column * 4;
+            data[offset..offset + 4].copy_from_slice(&argb.to_ne_bytes());
+        }
+    }
+
+    Ok(cairo::ImageSurface::create_for_data(
+        data,
+        cairo::Format::ARgb32,
+        width,
+        height,
+        stride as i32,
+    )?)
+}
+
+/// Images loaded by name, each backend keeps its own as cairo surfaces can't
+/// be shared between threads.
+#[derive(Default)]
+pub(crate) struct ImageCache {
+    images: HashMap<String, Source>,
+}
+
+impl ImageCache {
+    /// Loads a PNG or SVG file (by extension), replacing any image with the
+    /// same name.
+    pub fn load(&mut self, name: &str, path: &Path) -> Result<(), ImageError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let source = match extension.as_deref() {
+            Some("png") => {
+                let mut file = std::fs::File::open(path)?;
+                Source::Png(cairo::ImageSurface::create_from_png(&mut file)?)
+            }
+            Some("svg") => {
+                let data = std::fs::read(path)?;
+                let options = usvg::Options {
+                    resources_dir: path.parent().map(|p| p.to_path_buf()),
+                    ..usvg::Options::default()
+                };
+                Source::Svg {
+                    tree: usvg::Tree::from_data(&data, &options.to_ref())?,
+                    rendered: HashMap::new(),
+                }
+            }
+            _ => return Err(ImageError::UnknownFormat),
+        };
+
+        self.images.insert(name.to_owned(), source);
+        Ok(())
+    }
+
+    /// Size the image would be drawn with
+    pub fn size(&self, name: &str, style: &ImageStyle) -> Option<TextSize> {
+        let (width, height) = self.images.get(name)?.size();
+        Some(scaled_size(width, height, style))
+    }
+
+    pub fn draw(
+        &mut self,
+        context: &cairo::Context,
+        name: &str,
+        r#where: &Point,
+        style: &ImageStyle,
+    ) -> Option<TextSize> {
+        let source = self.images.get_mut(name)?;
+        let (width, height) = source.size();
+        let size = scaled_size(width, height, style);
+        if size.width < 1.0 || size.height < 1.0 {
+            return Some(size);
+        }
+
+        let surface = match source.surface(size.width.round() as i32, size.height.round() as i32) {
+            Ok(s) => s,
+            Err(e) => {
+                log::error!("Failed to render image {}: {:?}", name, e);
+                return None;
+            }
+        };
+
+        context.save();
+        context.translate(r#where.x, r#where.y);
+        context.scale(
+            size.width / surface.get_width() as f64,
+            size.height / surface.get_height() as f64,
+        );
+        match &style.tint {
+            Some(c) => {
+                context.set_source_rgba(c.red, c.green, c.blue, c.alpha * style.alpha);
+                context.mask_surface(surface, 0.0, 0.0);
+            }
+            None => {
+                context.set_source_surface(surface, 0.0, 0.0);
+                context.paint_with_alpha(style.alpha);
+            }
+        }
+        context.restore();
+
+        Some(size)
+    }
+}
+
+fn scaled_size(width: f64, height: f64, style: &ImageStyle) -> TextSize {
+    let (width, height) = match (style.width, style.height) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(w), None) if width > 0.0 => (w, height * w / width),
+        (None, Some(h)) if height > 0.0 => (width * h / height, h),
+        _ => (width, height),
+    };
+
+    TextSize { width, height }
+}
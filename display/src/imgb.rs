@@ -0,0 +1,396 @@
+use crate::{
+    draw,
+    fonts::{self, Fonts},
+    images::ImageCache,
+    Color, Display, Event, FontError, FontMetrics, Frame, ImageError, ImageStyle, Point, Rect,
+    Rotation, TextSize,
+};
+use std::path::{Path, PathBuf};
+
+/// Renders into an in-memory ARGB32 surface, so the output is pixel exact
+/// and does not depend on any framebuffer being present.
+pub struct CairoImage {
+    surface: cairo::ImageSurface,
+    context: Option<cairo::Context>,
+    rotation: Rotation,
+    images: ImageCache,
+    fonts: Fonts,
+    snapshot_path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum CairoImageError {
+    Cairo(String),
+    Io(String),
+}
+
+impl From<cairo::Error> for CairoImageError {
+    fn from(err: cairo::Error) -> Self {
+        CairoImageError::Cairo(format!("{}", err))
+    }
+}
+
+impl From<cairo::BorrowError> for CairoImageError {
+    fn from(err: cairo::BorrowError) -> Self {
+        CairoImageError::Cairo(format!("{}", err))
+    }
+}
+
+impl From<cairo::IoError> for CairoImageError {
+    fn from(err: cairo::IoError) -> Self {
+        CairoImageError::Io(format!("{}", err))
+    }
+}
+
+impl From<std::io::Error> for CairoImageError {
+    fn from(err: std::io::Error) -> Self {
+        CairoImageError::Io(format!("{}", err))
+    }
+}
+
+impl<'a> Display<'a> for CairoImage {
+    fn width(&self) -> usize {
+        let (width, height) = self.device_size();
+        self.rotation.size(width, height).0
+    }
+
+    fn height(&self) -> usize {
+        let (width, height) = self.device_size();
+        self.rotation.size(width, height).1
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        4
+    }
+
+    fn clean(&mut self) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.rectangle(0.0, 0.0, self.width() as f64, self.height() as f64);
+        context.fill();
+    }
+
+    fn start(&mut self) {
+        let (width, height) = self.device_size();
+        let context = cairo::Context::new(&self.surface);
+        self.rotation.apply(&context, width as f64, height as f64);
+        self.fonts.select(&context, fonts::DEFAULT_ROLE);
+        self.context = Some(context);
+    }
+
+    fn started(&self) -> bool {
+        self.context.is_some()
+    }
+
+    fn set_color(&mut self, color: &Color) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_source_rgba(color.red, color.green, color.blue, color.alpha);
+    }
+
+    fn text_size(&self, what: &str) -> TextSize {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.fonts.text_size(context, what)
+    }
+
+    fn render_text(&mut self, r#where: &Point, what: &str) -> Option<TextSize> {
+        if !self.started() {
+            return None;
+        }
+
+        let context = self.context.as_ref().unwrap();
+        context.move_to(r#where.x, r#where.y);
+        let size = self.fonts.text_size(context, what);
+        self.fonts.show_text(context, what);
+        Some(size)
+    }
+
+    fn set_font(&mut self, name: &str) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.fonts.select(context, name);
+    }
+
+    fn set_font_size(&mut self, size: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_font_size(size);
+    }
+
+    fn font_metrics(&self) -> FontMetrics {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        let extents = context.font_extents();
+        FontMetrics {
+            ascent: extents.ascent,
+            descent: extents.descent,
+            line_height: extents.height,
+        }
+    }
+
+    fn text_width(&self, what: &str) -> f64 {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.fonts.text_width(context, what)
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_line_width(width);
+    }
+
+    fn set_dash(&mut self, dashes: &[f64], offset: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_dash(dashes, offset);
+    }
+
+    fn draw_line(&mut self, from: &Point, to: &Point) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::line(context, from, to);
+    }
+
+    fn fill_rect(&mut self, rect: &Rect, radius: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::fill_rect(context, rect, radius);
+    }
+
+    fn stroke_rect(&mut self, rect: &Rect, radius: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::stroke_rect(context, rect, radius);
+    }
+
+    fn fill_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::fill_arc(context, center, radius, start, end);
+    }
+
+    fn stroke_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::stroke_arc(context, center, radius, start, end);
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::clip(context, rect);
+    }
+
+    fn reset_clip(&mut self) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.reset_clip();
+    }
+
+    fn load_image(&mut self, name: &str, path: &std::path::Path) -> Result<(), ImageError> {
+        self.images.load(name, path)
+    }
+
+    fn load_font(&mut self, role: &str, paths: &[std::path::PathBuf]) -> Result<(), FontError> {
+        self.fonts.load(role, paths)
+    }
+
+    fn image_size(&self, name: &str, style: &ImageStyle) -> Option<TextSize> {
+        self.images.size(name, style)
+    }
+
+    fn render_image(
+        &mut self,
+        r#where: &Point,
+        name: &str,
+        style: &ImageStyle,
+    ) -> Option<TextSize> {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.images.draw(context, name, r#where, style)
+    }
+
+    fn finish(&mut self) {
+        self.context = None;
+        self.surface.flush();
+
+        if let Some(path) = self.snapshot_path.as_ref() {
+            if let Err(e) = self.save_png(path) {
+                log::warn!("Failed to write snapshot to {:?}: {:?}", path, e);
+            }
+        }
+    }
+
+    fn frame(&self) -> Option<Frame> {
+        let (width, height) = self.device_size();
+        let mut frame = None;
+        self.surface
+            .with_data(|data| {
+                frame = Frame::from_surface_data(
+                    data,
+                    cairo_sys::FORMAT_A_RGB32,
+                    self.stride(),
+                    width,
+                    height,
+                    self.rotation,
+                );
+            })
+            .ok()?;
+        frame
+    }
+
+    fn init_events(&mut self) {}
+
+    fn get_events(&mut self) -> Vec<Event> {
+        vec![]
+    }
+
+    type DrawingBackend = plotters_cairo::CairoBackend<'a>;
+    type BackendError = plotters_cairo::CairoError;
+    fn get_backend(&'a self) -> Result<Self::DrawingBackend, Self::BackendError> {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        plotters_cairo::CairoBackend::new(context, (self.width() as u32, self.height() as u32))
+    }
+}
+
+impl CairoImage {
+    pub fn new(width: usize, height: usize) -> Result<Self, CairoImageError> {
+        let surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
+        Ok(Self {
+            surface,
+            context: None,
+            rotation: Rotation::Rotate0,
+            images: ImageCache::default(),
+            fonts: Fonts::default(),
+            snapshot_path: None,
+        })
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Every finished frame is written as PNG to `path` (`None` disables it).
+    pub fn set_snapshot_path(&mut self, path: Option<PathBuf>) {
+        self.snapshot_path = path;
+    }
+
+    pub fn write_png<W: std::io::Write>(&self, stream: &mut W) -> Result<(), CairoImageError> {
+        self.surface.write_to_png(stream)?;
+        Ok(())
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), CairoImageError> {
+        let mut file = std::fs::File::create(path)?;
+        self.write_png(&mut file)
+    }
+
+    pub(crate) fn device_size(&self) -> (usize, usize) {
+        (
+            self.surface.get_width() as usize,
+            self.surface.get_height() as usize,
+        )
+    }
+
+    pub fn stride(&self) -> usize {
+        self.surface.get_stride() as usize
+    }
+
+    /// Gives access to the raw (native endian, premultiplied ARGB32) pixels
+    /// of the last rendered frame.
+    pub fn with_data<F: FnOnce(&[u8])>(&self, f: F) -> Result<(), CairoImageError> {
+        self.surface.with_data(f)?;
+        Ok(())
+    }
+
+    /// Returns a pixel of the last rendered frame as `0xAARRGGBB`,
+    /// coordinates are not affected by rotation.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u32> {
+        let (width, height) = self.device_size();
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let offset = y * self.stride() + x * 4;
+        let mut pixel = None;
+        self.surface
+            .with_data(|data| {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&data[offset..offset + 4]);
+                pixel = Some(u32::from_ne_bytes(bytes));
+            })
+            .ok()?;
+        pixel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_renders_pixels() {
+        let mut img = CairoImage::new(8, 4).unwrap();
+        img.start();
+        img.set_color(&Color {
+            red: 0.0,
+            green: 0.0,
+            blue: 1.0,
+            alpha: 1.0,
+        });
+        img.clean();
+        img.finish();
+
+        assert_eq!(Some(0xff0000ff), img.pixel(0, 0));
+        assert_eq!(Some(0xff0000ff), img.pixel(7, 3));
+        assert_eq!(None, img.pixel(8, 0));
+
+        let mut png = Vec::new();
+        img.write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn svg_image_is_scaled_and_tinted() {
+        // Unique per process, so parallel test runs don't share the file
+        let path = std::env::temp_dir().join(format!("fb4rasp_square_{}.svg", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="2" height="2">
+                <rect width="2" height="2" fill="black"/>
+            </svg>"#,
+        )
+        .unwrap();
+
+        let mut img = CairoImage::new(8, 4).unwrap();
+        img.load_image("square", &path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        img.start();
+        let style = ImageStyle {
+            height: Some(4.0),
+            tint: Some(Color {
+                red: 1.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 1.0,
+            }),
+            ..ImageStyle::default()
+        };
+        let size = img
+            .render_image(&Point { x: 4.0, y: 0.0 }, "square", &style)
+            .unwrap();
+        assert!(img
+            .render_image(&Point { x: 0.0, y: 0.0 }, "missing", &style)
+            .is_none());
+        img.finish();
+
+        assert_eq!((4.0, 4.0), (size.width, size.height));
+        assert_eq!(Some(0), img.pixel(3, 3));
+        assert_eq!(Some(0xffff0000), img.pixel(4, 0));
+        assert_eq!(Some(0xffff0000), img.pixel(7, 3));
+    }
+}
@@ -1,127 +1,153 @@
-#[allow(dead_code)]
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
-pub(crate) enum Type {
-    EV_SYN = 0x00,
-    EV_KEY = 0x01,
-    EV_REL = 0x02,
-    EV_ABS = 0x03,
-    EV_MSC = 0x04,
-    EV_SW = 0x05,
-    EV_LED = 0x11,
-    EV_SND = 0x12,
-    EV_REP = 0x14,
-    EV_FF = 0x15,
-    EV_PWR = 0x16,
-    EV_FF_STATUS = 0x17,
-}
+use crate::{EventType, Point};
 
-impl num_traits::cast::FromPrimitive for Type {
-    fn from_i64(n: i64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
-    }
+/// Defines an enum of evdev codes together with a checked conversion from
+/// the raw value, codes missing in the table are `None`.
+macro_rules! codes {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident = $value:literal,)* }) => {
+        $(#[$meta])*
+        #[allow(dead_code)]
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub(crate) enum $name {
+            $($variant = $value,)*
+        }
+
+        impl $name {
+            pub fn from_code(code: u16) -> Option<Self> {
+                match code {
+                    $($value => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
 
-    fn from_u64(n: u64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
+codes! {
+    /// Event types
+    Type {
+        EV_SYN = 0x00,
+        EV_KEY = 0x01,
+        EV_REL = 0x02,
+        EV_ABS = 0x03,
+        EV_MSC = 0x04,
+        EV_SW = 0x05,
+        EV_LED = 0x11,
+        EV_SND = 0x12,
+        EV_REP = 0x14,
+        EV_FF = 0x15,
+        EV_PWR = 0x16,
+        EV_FF_STATUS = 0x17,
     }
 }
 
-/*
- * Relative axes
- */
-#[allow(dead_code)]
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
-pub(crate) enum Rel {
-    REL_X = 0x00,
-    REL_Y = 0x01,
-    REL_Z = 0x02,
-    REL_RX = 0x03,
-    REL_RY = 0x04,
-    REL_RZ = 0x05,
-    REL_HWHEEL = 0x06,
-    REL_DIAL = 0x07,
-    REL_WHEEL = 0x08,
-    REL_MISC = 0x09,
-    REL_MAX = 0x0f,
+codes! {
+    /// Synchronization events
+    Syn {
+        SYN_REPORT = 0x00,
+        SYN_CONFIG = 0x01,
+        SYN_MT_REPORT = 0x02,
+        SYN_DROPPED = 0x03,
+    }
 }
 
-impl num_traits::cast::FromPrimitive for Rel {
-    fn from_i64(n: i64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
+codes! {
+    /// Relative axes
+    Rel {
+        REL_X = 0x00,
+        REL_Y = 0x01,
+        REL_Z = 0x02,
+        REL_RX = 0x03,
+        REL_RY = 0x04,
+        REL_RZ = 0x05,
+        REL_HWHEEL = 0x06,
+        REL_DIAL = 0x07,
+        REL_WHEEL = 0x08,
+        REL_MISC = 0x09,
+        REL_RESERVED = 0x0a,
+        REL_WHEEL_HI_RES = 0x0b,
+        REL_HWHEEL_HI_RES = 0x0c,
     }
+}
 
-    fn from_u64(n: u64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
+codes! {
+    /// Absolute axes
+    Abs {
+        ABS_X = 0x00,
+        ABS_Y = 0x01,
+        ABS_Z = 0x02,
+        ABS_RX = 0x03,
+        ABS_RY = 0x04,
+        ABS_RZ = 0x05,
+        ABS_THROTTLE = 0x06,
+        ABS_RUDDER = 0x07,
+        ABS_WHEEL = 0x08,
+        ABS_GAS = 0x09,
+        ABS_BRAKE = 0x0a,
+        ABS_HAT0X = 0x10,
+        ABS_HAT0Y = 0x11,
+        ABS_HAT1X = 0x12,
+        ABS_HAT1Y = 0x13,
+        ABS_HAT2X = 0x14,
+        ABS_HAT2Y = 0x15,
+        ABS_HAT3X = 0x16,
+        ABS_HAT3Y = 0x17,
+        ABS_PRESSURE = 0x18,
+        ABS_DISTANCE = 0x19,
+        ABS_TILT_X = 0x1a,
+        ABS_TILT_Y = 0x1b,
+        ABS_TOOL_WIDTH = 0x1c,
+        ABS_VOLUME = 0x20,
+        ABS_PROFILE = 0x21,
+        ABS_MISC = 0x28,
+        ABS_RESERVED = 0x2e,
+        ABS_MT_SLOT = 0x2f,        /* MT slot being modified */
+        ABS_MT_TOUCH_MAJOR = 0x30, /* Major axis of touching ellipse */
+        ABS_MT_TOUCH_MINOR = 0x31, /* Minor axis (omit if circular) */
+        ABS_MT_WIDTH_MAJOR = 0x32, /* Major axis of approaching ellipse */
+        ABS_MT_WIDTH_MINOR = 0x33, /* Minor axis (omit if circular) */
+        ABS_MT_ORIENTATION = 0x34, /* Ellipse orientation */
+        ABS_MT_POSITION_X = 0x35,  /* Center X touch position */
+        ABS_MT_POSITION_Y = 0x36,  /* Center Y touch position */
+        ABS_MT_TOOL_TYPE = 0x37,   /* Type of touching device */
+        ABS_MT_BLOB_ID = 0x38,     /* Group a set of packets as a blob */
+        ABS_MT_TRACKING_ID = 0x39, /* Unique ID of initiated contact */
+        ABS_MT_PRESSURE = 0x3a,    /* Pressure on contact area */
+        ABS_MT_DISTANCE = 0x3b,    /* Contact hover distance */
+        ABS_MT_TOOL_X = 0x3c,      /* Center X tool position */
+        ABS_MT_TOOL_Y = 0x3d,      /* Center Y tool position */
     }
 }
 
-/*
- * Absolute axes
- */
-#[allow(dead_code)]
-#[allow(non_camel_case_types)]
-#[derive(Debug)]
-pub(crate) enum Abs {
-    ABS_X = 0x00,
-    ABS_Y = 0x01,
-    ABS_Z = 0x02,
-    ABS_RX = 0x03,
-    ABS_RY = 0x04,
-    ABS_RZ = 0x05,
-    ABS_THROTTLE = 0x06,
-    ABS_RUDDER = 0x07,
-    ABS_WHEEL = 0x08,
-    ABS_GAS = 0x09,
-    ABS_BRAKE = 0x0a,
-    ABS_HAT0X = 0x10,
-    ABS_HAT0Y = 0x11,
-    ABS_HAT1X = 0x12,
-    ABS_HAT1Y = 0x13,
-    ABS_HAT2X = 0x14,
-    ABS_HAT2Y = 0x15,
-    ABS_HAT3X = 0x16,
-    ABS_HAT3Y = 0x17,
-    ABS_PRESSURE = 0x18,
-    ABS_DISTANCE = 0x19,
-    ABS_TILT_X = 0x1a,
-    ABS_TILT_Y = 0x1b,
-    ABS_TOOL_WIDTH = 0x1c,
-    ABS_VOLUME = 0x20,
-    ABS_MISC = 0x28,
-    ABS_MT_SLOT = 0x2f,        /* MT slot being modified */
-    ABS_MT_TOUCH_MAJOR = 0x30, /* Major axis of touching ellipse */
-    ABS_MT_TOUCH_MINOR = 0x31, /* Minor axis (omit if circular) */
-    ABS_MT_WIDTH_MAJOR = 0x32, /* Major axis of approaching ellipse */
-    ABS_MT_WIDTH_MINOR = 0x33, /* Minor axis (omit if circular) */
-    ABS_MT_ORIENTATION = 0x34, /* Ellipse orientation */
-    ABS_MT_POSITION_X = 0x35,  /* Center X touch position */
-    ABS_MT_POSITION_Y = 0x36,  /* Center Y touch position */
-    ABS_MT_TOOL_TYPE = 0x37,   /* Type of touching device */
-    ABS_MT_BLOB_ID = 0x38,     /* Group a set of packets as a blob */
-    ABS_MT_TRACKING_ID = 0x39, /* Unique ID of initiated contact */
-    ABS_MT_PRESSURE = 0x3a,    /* Pressure on contact area */
-    ABS_MT_DISTANCE = 0x3b,    /* Contact hover distance */
-    ABS_MT_TOOL_X = 0x3c,      /* Center X tool position */
-    ABS_MT_TOOL_Y = 0x3d,      /* Center Y tool position */
-    ABS_MAX = 0x3f,
-}
-
-impl num_traits::cast::FromPrimitive for Abs {
-    fn from_i64(n: i64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
-    }
-
-    fn from_u64(n: u64) -> Option<Self> {
-        Some(unsafe { std::mem::transmute::<u8, Self>(n as u8) })
+codes! {
+    /// Miscellaneous events
+    Msc {
+        MSC_SERIAL = 0x00,
+        MSC_PULSELED = 0x01,
+        MSC_GESTURE = 0x02,
+        MSC_RAW = 0x03,
+        MSC_SCAN = 0x04,
+        MSC_TIMESTAMP = 0x05,
     }
 }
 
-#[derive(Debug)]
+pub(crate) const BTN_TOUCH: u16 = 0x14a;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum EvType {
-    Unknown(Type),
+    Sync(Syn),
+    /// Keys and buttons are kept as codes, there are too many of them
+    Key(u16),
     Relative(Rel),
     Absolute(Abs),
+    Misc(Msc),
+    /// Known type whose codes are not decoded (switches, LEDs, ...)
+    Other(Type, u16),
+    /// Type or code unknown to the decoder, kept as received
+    Unknown {
+        r#type: u16,
+        code: u16,
+    },
 }
 
 #[derive(Debug)]
@@ -133,19 +159,529 @@ pub(crate) struct Event {
 
 impl From<evdev::raw::input_event> for Event {
     fn from(ie: evdev::raw::input_event) -> Self {
-        use num_traits::cast::FromPrimitive;
-
-        let t = Type::from_u16(ie._type).unwrap();
-        let ev_type = match t {
-            Type::EV_REL => EvType::Relative(Rel::from_u16(ie.code).unwrap()),
-            Type::EV_ABS => EvType::Absolute(Abs::from_u16(ie.code).unwrap()),
-            t => EvType::Unknown(t),
+        let ev_type = match Type::from_code(ie._type) {
+            Some(Type::EV_SYN) => Syn::from_code(ie.code).map(EvType::Sync),
+            Some(Type::EV_KEY) => Some(EvType::Key(ie.code)),
+            Some(Type::EV_REL) => Rel::from_code(ie.code).map(EvType::Relative),
+            Some(Type::EV_ABS) => Abs::from_code(ie.code).map(EvType::Absolute),
+            Some(Type::EV_MSC) => Msc::from_code(ie.code).map(EvType::Misc),
+            Some(t) => Some(EvType::Other(t, ie.code)),
+            None => None,
         };
 
         Event {
             useconds: ie.time.tv_sec as i64 * 1000000 + ie.time.tv_usec as i64,
-            r#type: ev_type,
+            r#type: ev_type.unwrap_or(EvType::Unknown {
+                r#type: ie._type,
+                code: ie.code,
+            }),
             value: ie.value,
         }
     }
 }
+
+/*
+ * Keys and buttons usable for navigation
+ */
+const KEYS: &[(&str, u16)] = &[
+    ("KEY_ESC", 1),
+    ("KEY_1", 2),
+    ("KEY_2", 3),
+    ("KEY_3", 4),
+    ("KEY_4", 5),
+    ("KEY_5", 6),
+    ("KEY_6", 7),
+    ("KEY_7", 8),
+    ("KEY_8", 9),
+    ("KEY_9", 10),
+    ("KEY_0", 11),
+    ("KEY_BACKSPACE", 14),
+    ("KEY_TAB", 15),
+    ("KEY_ENTER", 28),
+    ("KEY_SPACE", 57),
+    ("KEY_F1", 59),
+    ("KEY_F2", 60),
+    ("KEY_F3", 61),
+    ("KEY_F4", 62),
+    ("KEY_F5", 63),
+    ("KEY_F6", 64),
+    ("KEY_F7", 65),
+    ("KEY_F8", 66),
+    ("KEY_F9", 67),
+    ("KEY_F10", 68),
+    ("KEY_KP7", 71),
+    ("KEY_KP8", 72),
+    ("KEY_KP9", 73),
+    ("KEY_KPMINUS", 74),
+    ("KEY_KP4", 75),
+    ("KEY_KP5", 76),
+    ("KEY_KP6", 77),
+    ("KEY_KPPLUS", 78),
+    ("KEY_KP1", 79),
+    ("KEY_KP2", 80),
+    ("KEY_KP3", 81),
+    ("KEY_KP0", 82),
+    ("KEY_KPENTER", 96),
+    ("KEY_HOME", 102),
+    ("KEY_UP", 103),
+    ("KEY_PAGEUP", 104),
+    ("KEY_LEFT", 105),
+    ("KEY_RIGHT", 106),
+    ("KEY_END", 107),
+    ("KEY_DOWN", 108),
+    ("KEY_PAGEDOWN", 109),
+    ("KEY_POWER", 116),
+    ("KEY_MENU", 139),
+    ("KEY_BACK", 158),
+    ("KEY_NEXTSONG", 163),
+    ("KEY_PLAYPAUSE", 164),
+    ("KEY_PREVIOUSSONG", 165),
+    ("BTN_0", 0x100),
+    ("BTN_1", 0x101),
+    ("BTN_2", 0x102),
+    ("BTN_3", 0x103),
+    ("BTN_4", 0x104),
+    ("BTN_5", 0x105),
+    ("BTN_6", 0x106),
+    ("BTN_7", 0x107),
+    ("BTN_8", 0x108),
+    ("BTN_9", 0x109),
+    ("BTN_LEFT", 0x110),
+    ("BTN_RIGHT", 0x111),
+    ("BTN_MIDDLE", 0x112),
+    ("KEY_OK", 0x160),
+    ("KEY_SELECT", 0x161),
+    ("KEY_NEXT", 0x197),
+    ("KEY_PREVIOUS", 0x19c),
+];
+
+/// Digitizer buttons (BTN_TOOL_*, BTN_TOUCH, ...) belong to touch handling
+const BTN_DIGI: std::ops::RangeInclusive<u16> = 0x140..=0x14f;
+
+/// Returns the code of a key given by its name from linux/input-event-codes.h
+/// (e.g. `KEY_ENTER`) or by its number.
+pub fn key_code(name: &str) -> Option<u16> {
+    KEYS.iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, code)| *code)
+        .or_else(|| match name.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => name.parse().ok(),
+        })
+}
+
+/// Converts key presses and rotary encoder steps, touch related events are
+/// left to `TouchTracker`.
+pub(crate) fn control_event(e: &Event) -> Option<EventType> {
+    match e.r#type {
+        EvType::Key(code) if !BTN_DIGI.contains(&code) => Some(match e.value {
+            0 => EventType::KeyUp(code),
+            // Pressed or autorepeat
+            _ => EventType::KeyDown(code),
+        }),
+        EvType::Relative(Rel::REL_DIAL) | EvType::Relative(Rel::REL_WHEEL) => {
+            Some(EventType::Rotate(e.value))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Contact {
+    Down,
+    Move,
+    Up,
+}
+
+/// State of a single contact at the end of an input frame (i.e. at
+/// SYN_REPORT), `slot` identifies the contact for its whole lifetime.
+#[derive(Debug, Clone)]
+pub(crate) struct TouchSample {
+    pub useconds: i64,
+    pub slot: usize,
+    pub contact: Contact,
+    pub position: Point,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    tracking_id: Option<i32>,
+    x: Option<i32>,
+    y: Option<i32>,
+    began: bool,
+    ended: bool,
+    moved: bool,
+}
+
+/// Assembles touch events into samples. Multi-touch (protocol B) devices
+/// are tracked per slot, single touch ones (ABS_X, ABS_Y and BTN_TOUCH) are
+/// reported as slot 0. Axes which did not change are not repeated by the
+/// kernel.
+#[derive(Debug, Default)]
+pub(crate) struct TouchTracker {
+    slots: Vec<Slot>,
+    current_slot: usize,
+    /// Legacy single touch events are only pointer emulation once any
+    /// ABS_MT_* event was seen
+    multitouch: bool,
+    /// Events were lost, everything up to the next SYN_REPORT is dropped
+    dropped: bool,
+}
+
+impl TouchTracker {
+    fn slot(&mut self, index: usize) -> &mut Slot {
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, Slot::default);
+        }
+        &mut self.slots[index]
+    }
+
+    pub fn process(&mut self, e: &Event) -> Vec<TouchSample> {
+        if self.dropped {
+            if e.r#type == EvType::Sync(Syn::SYN_REPORT) {
+                self.dropped = false;
+            }
+            return vec![];
+        }
+
+        let current = self.current_slot;
+        match e.r#type {
+            EvType::Absolute(Abs::ABS_MT_SLOT) => {
+                self.multitouch = true;
+                self.current_slot = e.value.max(0) as usize;
+            }
+            EvType::Absolute(Abs::ABS_MT_TRACKING_ID) => {
+                self.multitouch = true;
+                let slot = self.slot(current);
+                if e.value < 0 {
+                    slot.ended = slot.tracking_id.take().is_some();
+                } else {
+                    slot.began = slot.tracking_id != Some(e.value);
+                    slot.tracking_id = Some(e.value);
+                }
+            }
+            EvType::Absolute(Abs::ABS_MT_POSITION_X) => {
+                self.multitouch = true;
+                let slot = self.slot(current);
+                slot.x = Some(e.value);
+                slot.moved = true;
+            }
+            EvType::Absolute(Abs::ABS_MT_POSITION_Y) => {
+                self.multitouch = true;
+                let slot = self.slot(current);
+                slot.y = Some(e.value);
+                slot.moved = true;
+            }
+            EvType::Absolute(Abs::ABS_X) if !self.multitouch => {
+                let slot = self.slot(0);
+                slot.x = Some(e.value);
+                slot.moved = true;
+            }
+            EvType::Absolute(Abs::ABS_Y) if !self.multitouch => {
+                let slot = self.slot(0);
+                slot.y = Some(e.value);
+                slot.moved = true;
+            }
+            EvType::Key(BTN_TOUCH) if !self.multitouch => {
+                let slot = self.slot(0);
+                if e.value != 0 {
+                    slot.began = true;
+                    slot.tracking_id = Some(0);
+                } else {
+                    slot.ended = slot.tracking_id.take().is_some();
+                }
+            }
+            EvType::Sync(Syn::SYN_REPORT) => return self.report(e.useconds),
+            EvType::Sync(Syn::SYN_DROPPED) => {
+                log::warn!("Input events were dropped");
+                self.dropped = true;
+                for slot in self.slots.iter_mut() {
+                    slot.began = false;
+                    slot.moved = false;
+                }
+            }
+            _ => (),
+        }
+
+        vec![]
+    }
+
+    fn report(&mut self, useconds: i64) -> Vec<TouchSample> {
+        let multitouch = self.multitouch;
+        let mut samples = vec![];
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let began = std::mem::replace(&mut slot.began, false);
+            let ended = std::mem::replace(&mut slot.ended, false);
+            let moved = std::mem::replace(&mut slot.moved, false);
+
+            let position = match (slot.x, slot.y) {
+                (Some(x), Some(y)) => Point {
+                    x: x as f64,
+                    y: y as f64,
+                },
+                _ => continue,
+            };
+
+            let mut sample = |contact| {
+                samples.push(TouchSample {
+                    useconds,
+                    slot: index,
+                    contact,
+                    position: position.clone(),
+                })
+            };
+
+            if began {
+                sample(Contact::Down);
+            } else if moved && (slot.tracking_id.is_some() || (!multitouch && !ended)) {
+                // Single touch devices without BTN_TOUCH only report movement
+                sample(Contact::Move);
+            }
+            if ended {
+                sample(Contact::Up);
+            }
+        }
+
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gesture::GestureRecognizer;
+
+    const EV_SYN: u16 = 0x00;
+    const EV_KEY: u16 = 0x01;
+    const EV_REL: u16 = 0x02;
+    const EV_ABS: u16 = 0x03;
+    const EV_MSC: u16 = 0x04;
+    const EV_SW: u16 = 0x05;
+    const SYN_REPORT: u16 = 0x00;
+
+    /// Feeds `(seconds, useconds, type, code, value)` records, as dumped by
+    /// evtest, into the decoder.
+    fn decode(recording: &[(i64, i64, u16, u16, i32)]) -> Vec<TouchSample> {
+        let mut tracker = TouchTracker::default();
+        recording
+            .iter()
+            .flat_map(|&(sec, usec, _type, code, value)| {
+                let e: Event = evdev::raw::input_event {
+                    time: libc::timeval {
+                        tv_sec: sec as libc::time_t,
+                        tv_usec: usec as libc::suseconds_t,
+                    },
+                    _type,
+                    code,
+                    value,
+                }
+                .into();
+                tracker.process(&e)
+            })
+            .collect()
+    }
+
+    fn event(_type: u16, code: u16, value: i32) -> Event {
+        evdev::raw::input_event {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            _type,
+            code,
+            value,
+        }
+        .into()
+    }
+
+    fn summary(samples: &[TouchSample]) -> Vec<(usize, Contact, f64, f64)> {
+        samples
+            .iter()
+            .map(|s| (s.slot, s.contact, s.position.x, s.position.y))
+            .collect()
+    }
+
+    #[test]
+    fn events_are_decoded() {
+        let unknown = |r#type, code| EvType::Unknown { r#type, code };
+        let table = [
+            (EV_SYN, 0x00, EvType::Sync(Syn::SYN_REPORT)),
+            (EV_SYN, 0x02, EvType::Sync(Syn::SYN_MT_REPORT)),
+            (EV_SYN, 0x03, EvType::Sync(Syn::SYN_DROPPED)),
+            (EV_SYN, 0x0f, unknown(EV_SYN, 0x0f)),
+            (EV_KEY, 0x1c, EvType::Key(0x1c)),
+            (EV_KEY, BTN_TOUCH, EvType::Key(BTN_TOUCH)),
+            (EV_KEY, 0x2ff, EvType::Key(0x2ff)),
+            (EV_REL, 0x00, EvType::Relative(Rel::REL_X)),
+            (EV_REL, 0x07, EvType::Relative(Rel::REL_DIAL)),
+            (EV_REL, 0x0c, EvType::Relative(Rel::REL_HWHEEL_HI_RES)),
+            (EV_REL, 0x0d, unknown(EV_REL, 0x0d)),
+            // Would be truncated to REL_DIAL by a cast to u8
+            (EV_REL, 0x107, unknown(EV_REL, 0x107)),
+            (EV_ABS, 0x00, EvType::Absolute(Abs::ABS_X)),
+            (EV_ABS, 0x2f, EvType::Absolute(Abs::ABS_MT_SLOT)),
+            (EV_ABS, 0x39, EvType::Absolute(Abs::ABS_MT_TRACKING_ID)),
+            (EV_ABS, 0x3d, EvType::Absolute(Abs::ABS_MT_TOOL_Y)),
+            (EV_ABS, 0x0b, unknown(EV_ABS, 0x0b)),
+            (EV_ABS, 0x3e, unknown(EV_ABS, 0x3e)),
+            (EV_ABS, 0x135, unknown(EV_ABS, 0x135)),
+            (EV_MSC, 0x04, EvType::Misc(Msc::MSC_SCAN)),
+            (EV_MSC, 0x05, EvType::Misc(Msc::MSC_TIMESTAMP)),
+            (EV_MSC, 0x07, unknown(EV_MSC, 0x07)),
+            (EV_SW, 0x00, EvType::Other(Type::EV_SW, 0x00)),
+            (0x11, 0x01, EvType::Other(Type::EV_LED, 0x01)),
+            (0x17, 0x00, EvType::Other(Type::EV_FF_STATUS, 0x00)),
+            (0x06, 0x00, unknown(0x06, 0x00)),
+            (0x1f, 0x00, unknown(0x1f, 0x00)),
+            // Would be EV_KEY after a cast to u8
+            (0x101, 0x1c, unknown(0x101, 0x1c)),
+            (0xffff, 0xffff, unknown(0xffff, 0xffff)),
+        ];
+
+        for &(_type, code, expected) in table.iter() {
+            let e = event(_type, code, -7);
+            assert_eq!(expected, e.r#type, "type {:#x}, code {:#x}", _type, code);
+            assert_eq!(-7, e.value);
+        }
+    }
+
+    #[test]
+    fn dropped_events_are_skipped() {
+        let mut tracker = TouchTracker::default();
+        let events = [
+            event(EV_ABS, Abs::ABS_X as u16, 10),
+            event(EV_ABS, Abs::ABS_Y as u16, 20),
+            event(EV_KEY, BTN_TOUCH, 1),
+            event(EV_SYN, Syn::SYN_DROPPED as u16, 0),
+            event(EV_ABS, Abs::ABS_X as u16, 30),
+            event(EV_SYN, SYN_REPORT, 0),
+            event(EV_ABS, Abs::ABS_X as u16, 40),
+            event(EV_SYN, SYN_REPORT, 0),
+        ];
+        let samples: Vec<TouchSample> = events.iter().flat_map(|e| tracker.process(e)).collect();
+        assert_eq!(vec![(0, Contact::Move, 40.0, 20.0)], summary(&samples));
+    }
+
+    #[test]
+    fn keys_are_decoded() {
+        assert_eq!(Some(28), key_code("KEY_ENTER"));
+        assert_eq!(Some(0x161), key_code("KEY_SELECT"));
+        assert_eq!(Some(0x2b), key_code("0x2b"));
+        assert_eq!(Some(43), key_code("43"));
+        assert_eq!(None, key_code("KEY_UNKNOWN"));
+
+        assert_eq!(
+            Some(EventType::KeyDown(28)),
+            control_event(&event(EV_KEY, 28, 1))
+        );
+        assert_eq!(
+            Some(EventType::KeyDown(28)),
+            control_event(&event(EV_KEY, 28, 2))
+        );
+        assert_eq!(
+            Some(EventType::KeyUp(28)),
+            control_event(&event(EV_KEY, 28, 0))
+        );
+        assert_eq!(None, control_event(&event(EV_KEY, BTN_TOUCH, 1)));
+        assert_eq!(
+            Some(EventType::Rotate(-1)),
+            control_event(&event(EV_REL, Rel::REL_DIAL as u16, -1))
+        );
+        assert_eq!(None, control_event(&event(EV_REL, Rel::REL_X as u16, 5)));
+    }
+
+    #[test]
+    fn single_touch_is_decoded() {
+        let samples = decode(&[
+            (10, 0, EV_ABS, Abs::ABS_X as u16, 100),
+            (10, 0, EV_ABS, Abs::ABS_Y as u16, 200),
+            (10, 0, EV_KEY, BTN_TOUCH, 1),
+            (10, 0, EV_SYN, SYN_REPORT, 0),
+            (10, 20_000, EV_ABS, Abs::ABS_X as u16, 120),
+            (10, 20_000, EV_SYN, SYN_REPORT, 0),
+            (10, 40_000, EV_KEY, BTN_TOUCH, 0),
+            (10, 40_000, EV_SYN, SYN_REPORT, 0),
+        ]);
+
+        assert_eq!(
+            vec![
+                (0, Contact::Down, 100.0, 200.0),
+                (0, Contact::Move, 120.0, 200.0),
+                (0, Contact::Up, 120.0, 200.0)
+            ],
+            summary(&samples)
+        );
+        assert_eq!(10_040_000, samples[2].useconds);
+    }
+
+    #[test]
+    fn multi_touch_pinch_is_decoded() {
+        let slot = Abs::ABS_MT_SLOT as u16;
+        let id = Abs::ABS_MT_TRACKING_ID as u16;
+        let x = Abs::ABS_MT_POSITION_X as u16;
+        let y = Abs::ABS_MT_POSITION_Y as u16;
+        let samples = decode(&[
+            (1, 0, EV_ABS, slot, 0),
+            (1, 0, EV_ABS, id, 10),
+            (1, 0, EV_ABS, x, 100),
+            (1, 0, EV_ABS, y, 100),
+            (1, 0, EV_KEY, BTN_TOUCH, 1),
+            (1, 0, EV_ABS, Abs::ABS_X as u16, 100),
+            (1, 0, EV_ABS, Abs::ABS_Y as u16, 100),
+            (1, 0, EV_SYN, SYN_REPORT, 0),
+            (1, 20_000, EV_ABS, slot, 1),
+            (1, 20_000, EV_ABS, id, 11),
+            (1, 20_000, EV_ABS, x, 200),
+            (1, 20_000, EV_ABS, y, 100),
+            (1, 20_000, EV_SYN, SYN_REPORT, 0),
+            (1, 50_000, EV_ABS, slot, 0),
+            (1, 50_000, EV_ABS, x, 50),
+            (1, 50_000, EV_ABS, slot, 1),
+            (1, 50_000, EV_ABS, x, 250),
+            (1, 50_000, EV_ABS, Abs::ABS_X as u16, 50),
+            (1, 50_000, EV_SYN, SYN_REPORT, 0),
+            (1, 100_000, EV_ABS, slot, 0),
+            (1, 100_000, EV_ABS, id, -1),
+            (1, 100_000, EV_SYN, SYN_REPORT, 0),
+            (1, 120_000, EV_ABS, slot, 1),
+            (1, 120_000, EV_ABS, id, -1),
+            (1, 120_000, EV_KEY, BTN_TOUCH, 0),
+            (1, 120_000, EV_SYN, SYN_REPORT, 0),
+        ]);
+
+        assert_eq!(
+            vec![
+                (0, Contact::Down, 100.0, 100.0),
+                (1, Contact::Down, 200.0, 100.0),
+                (0, Contact::Move, 50.0, 100.0),
+                (1, Contact::Move, 250.0, 100.0),
+                (0, Contact::Up, 50.0, 100.0),
+                (1, Contact::Up, 250.0, 100.0)
+            ],
+            summary(&samples)
+        );
+
+        let mut recognizer = GestureRecognizer::default();
+        let gestures: Vec<EventType> = samples
+            .iter()
+            .flat_map(|s| recognizer.process(s))
+            .map(|e| e.what)
+            .collect();
+        assert_eq!(
+            vec![
+                EventType::ContactDown(0),
+                EventType::Touched,
+                EventType::ContactDown(1),
+                EventType::ContactMove(0),
+                EventType::Pinch { scale: 1.5 },
+                EventType::ContactMove(1),
+                EventType::Pinch {
+                    scale: 200.0 / 150.0
+                },
+                EventType::ContactUp(0),
+                EventType::ContactUp(1)
+            ],
+            gestures
+        );
+    }
+}
@@ -1,9 +1,37 @@
+mod calibration;
+mod draw;
 mod fb4rasp;
+mod fonts;
+mod frame;
+mod gesture;
+mod images;
+mod imgb;
 mod input;
+mod pixel;
+mod reader;
+mod rotation;
 mod svgb;
+mod termb;
+mod text;
 mod utils;
+mod vnc;
 
-pub use crate::{fb4rasp::Fb4Rasp, svgb::CairoSvg, utils::get_cpu_temperature};
+pub use crate::{
+    calibration::TouchCalibration,
+    fb4rasp::{Fb4Rasp, FramebufferInfo},
+    fonts::FontError,
+    frame::Frame,
+    images::{ImageError, ImageStyle},
+    imgb::{CairoImage, CairoImageError},
+    input::key_code,
+    reader::InputReader,
+    rotation::Rotation,
+    svgb::{CairoSvg, VectorFormat, VectorOutput},
+    termb::CairoTerminal,
+    text::{Align, FontMetrics, TextLayout, TextLine},
+    utils::get_cpu_temperature,
+    vnc::{VncInput, VncServer},
+};
 
 pub trait Display<'a> {
     fn width(&self) -> usize;
@@ -17,7 +45,60 @@ pub trait Display<'a> {
     fn render_text(&mut self, r#where: &Point, what: &str) -> Option<TextSize>;
     fn set_font(&mut self, name: &str);
     fn set_font_size(&mut self, size: f64);
+    fn font_metrics(&self) -> FontMetrics;
+    /// Horizontal advance of the text, i.e. where the next one would start
+    fn text_width(&self, what: &str) -> f64;
+    /// Places text into `rect`, without rendering it
+    fn layout_text(&self, rect: &Rect, what: &str, layout: &TextLayout) -> Vec<TextLine> {
+        text::layout(what, rect, layout, &self.font_metrics(), |s| {
+            self.text_width(s)
+        })
+    }
+    /// Renders text placed into `rect`, returns its lines
+    fn render_text_box(&mut self, rect: &Rect, what: &str, layout: &TextLayout) -> Vec<TextLine> {
+        let lines = self.layout_text(rect, what, layout);
+        for line in lines.iter() {
+            self.render_text(&line.baseline, &line.text);
+        }
+        lines
+    }
+    /// Width of lines, strokes and arcs
+    fn set_line_width(&mut self, width: f64);
+    /// Lengths of alternating dashes and gaps of lines, empty for solid ones
+    fn set_dash(&mut self, dashes: &[f64], offset: f64);
+    fn draw_line(&mut self, from: &Point, to: &Point);
+    /// Corners are rounded with `radius`, 0 gives sharp ones
+    fn fill_rect(&mut self, rect: &Rect, radius: f64);
+    fn stroke_rect(&mut self, rect: &Rect, radius: f64);
+    /// Fills a pie slice, angles are in radians, clockwise from the x axis
+    fn fill_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64);
+    fn stroke_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64);
+    fn fill_circle(&mut self, center: &Point, radius: f64) {
+        self.fill_arc(center, radius, 0.0, 2.0 * std::f64::consts::PI);
+    }
+    fn stroke_circle(&mut self, center: &Point, radius: f64) {
+        self.stroke_arc(center, radius, 0.0, 2.0 * std::f64::consts::PI);
+    }
+    /// Restricts drawing to `rect`, intersected with the current clip region
+    fn clip(&mut self, rect: &Rect);
+    fn reset_clip(&mut self);
+    /// Loads a PNG or SVG file once, to be rendered later by its name
+    fn load_image(&mut self, name: &str, path: &std::path::Path) -> Result<(), ImageError>;
+    /// Loads TrueType or OpenType files of a font role (regular, bold, mono,
+    /// icon or any other name) to be set by its name, glyphs missing in the
+    /// first file are taken from the following ones
+    fn load_font(&mut self, role: &str, paths: &[std::path::PathBuf]) -> Result<(), FontError>;
+    fn image_size(&self, name: &str, style: &ImageStyle) -> Option<TextSize>;
+    /// Renders a loaded image with its top left corner at `where`, `None` if
+    /// there is no such image
+    fn render_image(&mut self, r#where: &Point, name: &str, style: &ImageStyle)
+        -> Option<TextSize>;
     fn finish(&mut self);
+    /// Copy of the last finished frame, `None` if the backend can't read it
+    /// back
+    fn frame(&self) -> Option<Frame> {
+        None
+    }
     fn init_events(&mut self);
     fn get_events(&mut self) -> Vec<Event>;
 
@@ -26,12 +107,20 @@ pub trait Display<'a> {
     fn get_backend(&'a self) -> Result<Self::DrawingBackend, Self::BackendError>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Color {
     pub red: f64,
@@ -46,13 +135,41 @@ pub struct TextSize {
     pub height: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EventType {
+    /// Touch started
     Touched,
+    Tap,
+    LongPress,
+    SwipeLeft,
+    SwipeRight,
+    SwipeUp,
+    SwipeDown,
+    /// Touch moved by given delta since the previous drag event
+    Drag {
+        dx: f64,
+        dy: f64,
+    },
+    /// Distance between two fingers changed `scale` times since the previous
+    /// pinch event, position is the middle between them
+    Pinch {
+        scale: f64,
+    },
+    /// Lifecycle of every single contact (finger), identified by its slot
+    ContactDown(usize),
+    ContactMove(usize),
+    ContactUp(usize),
+    /// Key or button pressed (also repeated while held) and released, see
+    /// linux/input-event-codes.h for codes
+    KeyDown(u16),
+    KeyUp(u16),
+    /// Rotary encoder (dial or wheel) turned by given number of steps
+    Rotate(i32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Event {
     pub what: EventType,
+    /// Screen position, always (0, 0) for keys and rotary encoders
     pub position: Point,
 }
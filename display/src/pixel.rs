@@ -0,0 +1,191 @@
+/// Bit position and width of a colour channel within a framebuffer pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Channel {
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl From<&linuxfb::PixelLayoutChannel> for Channel {
+    fn from(ch: &linuxfb::PixelLayoutChannel) -> Self {
+        Self {
+            offset: ch.offset,
+            length: ch.length,
+        }
+    }
+}
+
+/// Memory layout of a single framebuffer pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Packing {
+    pub red: Channel,
+    pub green: Channel,
+    pub blue: Channel,
+    pub alpha: Channel,
+    pub bytes_per_pixel: usize,
+}
+
+impl Packing {
+    pub fn new(layout: &linuxfb::PixelLayout, bytes_per_pixel: usize) -> Self {
+        Self {
+            red: (&layout.red).into(),
+            green: (&layout.green).into(),
+            blue: (&layout.blue).into(),
+            alpha: (&layout.alpha).into(),
+            bytes_per_pixel,
+        }
+    }
+
+    fn rgb(&self) -> [(u32, u32); 3] {
+        [
+            (self.red.offset, self.red.length),
+            (self.green.offset, self.green.length),
+            (self.blue.offset, self.blue.length),
+        ]
+    }
+
+    fn pack_channel(value: u32, ch: Channel) -> u32 {
+        if ch.length == 0 {
+            return 0;
+        }
+
+        let value = if ch.length <= 8 {
+            value >> (8 - ch.length)
+        } else {
+            value << (ch.length - 8)
+        };
+        value << ch.offset
+    }
+
+    /// Converts a cairo ARGB32 pixel (native endian) to this layout.
+    pub fn pack(&self, argb: u32) -> u32 {
+        Self::pack_channel((argb >> 24) & 0xff, self.alpha)
+            | Self::pack_channel((argb >> 16) & 0xff, self.red)
+            | Self::pack_channel((argb >> 8) & 0xff, self.green)
+            | Self::pack_channel(argb & 0xff, self.blue)
+    }
+
+    /// Converts a whole ARGB32 frame, bytes between the last pixel of a line
+    /// and `dst_stride` are left untouched.
+    pub fn convert(
+        &self,
+        src: &[u8],
+        src_stride: usize,
+        dst: &mut [u8],
+        dst_stride: usize,
+        width: usize,
+        height: usize,
+    ) {
+        let bpp = self.bytes_per_pixel;
+        for y in 0..height {
+            let src_line = &src[y * src_stride..y * src_stride + width * 4];
+            let dst_line = &mut dst[y * dst_stride..y * dst_stride + width * bpp];
+            for (s, d) in src_line.chunks_exact(4).zip(dst_line.chunks_exact_mut(bpp)) {
+                let argb = u32::from_ne_bytes([s[0], s[1], s[2], s[3]]);
+                // Framebuffer pixels are stored little endian on our targets
+                d.copy_from_slice(&self.pack(argb).to_le_bytes()[..bpp]);
+            }
+        }
+    }
+}
+
+/// How cairo output ends up in the framebuffer memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PixelFormat {
+    /// Cairo can draw directly in the framebuffer layout
+    Native(cairo_sys::cairo_format_t),
+    /// Cairo draws ARGB32, which gets converted on every frame
+    Converted(Packing),
+}
+
+impl PixelFormat {
+    pub fn new(packing: Packing) -> Self {
+        const RGB565: [(u32, u32); 3] = [(11, 5), (5, 6), (0, 5)];
+        const RGB888: [(u32, u32); 3] = [(16, 8), (8, 8), (0, 8)];
+
+        match packing.bytes_per_pixel {
+            2 if packing.rgb() == RGB565 => PixelFormat::Native(cairo_sys::FORMAT_RGB16_565),
+            4 if packing.rgb() == RGB888 => {
+                if packing.alpha.length == 8 && packing.alpha.offset == 24 {
+                    PixelFormat::Native(cairo_sys::FORMAT_A_RGB32)
+                } else {
+                    PixelFormat::Native(cairo_sys::FORMAT_RGB24)
+                }
+            }
+            _ => PixelFormat::Converted(packing),
+        }
+    }
+
+    /// Format of the surface cairo draws into.
+    pub fn cairo_format(&self) -> cairo_sys::cairo_format_t {
+        match self {
+            PixelFormat::Native(format) => *format,
+            PixelFormat::Converted(_) => cairo_sys::FORMAT_A_RGB32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packing(rgb: [(u32, u32); 3], alpha: (u32, u32), bytes_per_pixel: usize) -> Packing {
+        let ch = |(offset, length)| Channel { offset, length };
+        Packing {
+            red: ch(rgb[0]),
+            green: ch(rgb[1]),
+            blue: ch(rgb[2]),
+            alpha: ch(alpha),
+            bytes_per_pixel,
+        }
+    }
+
+    #[test]
+    fn pixel_format_detection() {
+        let rgb565 = packing([(11, 5), (5, 6), (0, 5)], (0, 0), 2);
+        let bgr565 = packing([(0, 5), (5, 6), (11, 5)], (0, 0), 2);
+        let xrgb = packing([(16, 8), (8, 8), (0, 8)], (0, 0), 4);
+        let argb = packing([(16, 8), (8, 8), (0, 8)], (24, 8), 4);
+        let xbgr = packing([(0, 8), (8, 8), (16, 8)], (0, 0), 4);
+
+        assert_eq!(
+            PixelFormat::Native(cairo_sys::FORMAT_RGB16_565),
+            PixelFormat::new(rgb565)
+        );
+        assert_eq!(PixelFormat::Converted(bgr565), PixelFormat::new(bgr565));
+        assert_eq!(
+            PixelFormat::Native(cairo_sys::FORMAT_RGB24),
+            PixelFormat::new(xrgb)
+        );
+        assert_eq!(
+            PixelFormat::Native(cairo_sys::FORMAT_A_RGB32),
+            PixelFormat::new(argb)
+        );
+        assert_eq!(PixelFormat::Converted(xbgr), PixelFormat::new(xbgr));
+    }
+
+    #[test]
+    fn pixel_conversion() {
+        let bgr565 = packing([(0, 5), (5, 6), (11, 5)], (0, 0), 2);
+        assert_eq!(0x001f, bgr565.pack(0xffff0000));
+        assert_eq!(0xf800, bgr565.pack(0xff0000ff));
+
+        let xbgr = packing([(0, 8), (8, 8), (16, 8)], (0, 0), 4);
+        assert_eq!(0x00332211, xbgr.pack(0xff112233));
+
+        // Two RGB888 pixels per line, padded to 8 bytes
+        let rgb888 = packing([(16, 8), (8, 8), (0, 8)], (0, 0), 3);
+        let mut src = Vec::new();
+        for argb in &[0xff112233u32, 0xff445566, 0xff778899, 0xffaabbcc] {
+            src.extend_from_slice(&argb.to_ne_bytes());
+        }
+        let mut dst = vec![0xeeu8; 16];
+        rgb888.convert(&src, 8, &mut dst, 8, 2, 2);
+        assert_eq!(
+            vec![
+                0x33, 0x22, 0x11, 0x66, 0x55, 0x44, 0xee, 0xee, 0x99, 0x88, 0x77, 0xcc, 0xbb, 0xaa,
+                0xee, 0xee
+            ],
+            dst
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use crate::gesture::GestureRecognizer;
+use crate::{input, Event, Point, Rotation, TouchCalibration};
+use std::os::unix::io::{AsRawFd, RawFd};
+use tokio::io::unix::AsyncFd;
+
+/// Long press is checked this often while a touch is held
+const LONG_PRESS_POLL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Maps raw touchscreen readings to screen coordinates.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScreenMapping {
+    pub calibration: TouchCalibration,
+    pub rotation: Rotation,
+    /// Size of the framebuffer device (i.e. not rotated)
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ScreenMapping {
+    fn map(&self, raw: &Point) -> Point {
+        self.rotation.to_logical(
+            &self.calibration.map(raw, self.width, self.height),
+            self.width,
+            self.height,
+        )
+    }
+}
+
+/// Evdev device together with the state of touches on it.
+pub(crate) struct InputDevice {
+    device: evdev::Device,
+    tracker: input::TouchTracker,
+    gestures: GestureRecognizer,
+}
+
+impl AsRawFd for InputDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.device.fd()
+    }
+}
+
+impl InputDevice {
+    pub fn new(device: evdev::Device) -> Self {
+        Self {
+            device,
+            tracker: input::TouchTracker::default(),
+            gestures: GestureRecognizer::default(),
+        }
+    }
+
+    fn read_raw(&mut self) -> Vec<input::Event> {
+        match self.device.events() {
+            Ok(raw_events) => raw_events
+                .map(|event| {
+                    let e: input::Event = event.into();
+                    log::debug!("Raw event: {:?}", &e);
+                    e
+                })
+                .collect(),
+            Err(e) => {
+                log::debug!("error {:?} ", e);
+                vec![]
+            }
+        }
+    }
+
+    /// Uncalibrated touch samples pending on the device, never blocks.
+    pub fn read_samples(&mut self) -> Vec<input::TouchSample> {
+        let mut samples = vec![];
+        for e in self.read_raw() {
+            samples.extend(self.tracker.process(&e));
+        }
+
+        samples
+    }
+
+    /// Events pending on the device, never blocks.
+    pub fn read_events(&mut self, mapping: &ScreenMapping) -> Vec<Event> {
+        let mut events = vec![];
+        for e in self.read_raw() {
+            if let Some(what) = input::control_event(&e) {
+                events.push(Event {
+                    what,
+                    position: Point { x: 0.0, y: 0.0 },
+                });
+            }
+
+            for mut sample in self.tracker.process(&e) {
+                sample.position = mapping.map(&sample.position);
+                events.extend(self.gestures.process(&sample));
+            }
+        }
+
+        events.extend(self.gestures.poll(now_useconds()));
+        events
+    }
+
+    fn long_press_pending(&self) -> bool {
+        self.gestures.long_press_pending()
+    }
+}
+
+/// Input events are timestamped with the realtime clock
+fn now_useconds() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+/// Reads events of a single input device as they happen, to be driven by a
+/// tokio task.
+pub struct InputReader {
+    device: AsyncFd<InputDevice>,
+    mapping: ScreenMapping,
+}
+
+impl InputReader {
+    pub(crate) fn new(device: InputDevice, mapping: ScreenMapping) -> std::io::Result<Self> {
+        Ok(Self {
+            device: AsyncFd::new(device)?,
+            mapping,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.device
+            .get_ref()
+            .device
+            .name()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Waits for the next events of the device, fails only if the device
+    /// can't be polled anymore (e.g. it was unplugged).
+    pub async fn next_events(&mut self) -> std::io::Result<Vec<Event>> {
+        loop {
+            let readable = if self.device.get_ref().long_press_pending() {
+                match tokio::time::timeout(LONG_PRESS_POLL, self.device.readable_mut()).await {
+                    Ok(guard) => Some(guard?),
+                    Err(_) => None,
+                }
+            } else {
+                Some(self.device.readable_mut().await?)
+            };
+
+            let events = match readable {
+                Some(mut guard) => {
+                    let events = guard.get_inner_mut().read_events(&self.mapping);
+                    // The device is read until it would block
+                    guard.clear_ready();
+                    events
+                }
+                None => self.device.get_mut().read_events(&self.mapping),
+            };
+
+            if !events.is_empty() {
+                return Ok(events);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventType;
+    use std::os::unix::io::IntoRawFd;
+
+    const EV_SYN: u16 = 0x00;
+    const EV_KEY: u16 = 0x01;
+    const EV_REL: u16 = 0x02;
+    const KEY_ENTER: u16 = 28;
+    const KEY_UP: u16 = 103;
+    const REL_DIAL: u16 = 0x07;
+
+    const UI_SET_EVBIT: libc::c_ulong = 0x4004_5564;
+    const UI_SET_KEYBIT: libc::c_ulong = 0x4004_5565;
+    const UI_SET_RELBIT: libc::c_ulong = 0x4004_5566;
+    const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+    const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+
+    /// Keypad with a rotary encoder created through /dev/uinput
+    struct VirtualDevice {
+        fd: RawFd,
+    }
+
+    impl VirtualDevice {
+        fn create(name: &str) -> Self {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/uinput")
+                .expect("Failed to open /dev/uinput");
+            let fd = file.into_raw_fd();
+
+            unsafe {
+                assert_eq!(0, libc::ioctl(fd, UI_SET_EVBIT, EV_KEY as libc::c_int));
+                assert_eq!(0, libc::ioctl(fd, UI_SET_EVBIT, EV_REL as libc::c_int));
+                for key in &[KEY_ENTER, KEY_UP] {
+                    assert_eq!(0, libc::ioctl(fd, UI_SET_KEYBIT, *key as libc::c_int));
+                }
+                assert_eq!(0, libc::ioctl(fd, UI_SET_RELBIT, REL_DIAL as libc::c_int));
+            }
+
+            // struct uinput_user_dev: name, input_id, ff_effects_max and
+            // 4 arrays of ABS_CNT axis limits
+            let mut setup = vec![0u8; 80 + 8 + 4 + 4 * 64 * 4];
+            setup[..name.len()].copy_from_slice(name.as_bytes());
+            // BUS_VIRTUAL
+            setup[80] = 0x06;
+            unsafe {
+                assert_eq!(
+                    setup.len() as isize,
+                    libc::write(fd, setup.as_ptr() as *const libc::c_void, setup.len())
+                );
+                assert_eq!(0, libc::ioctl(fd, UI_DEV_CREATE));
+            }
+
+            Self { fd }
+        }
+
+        fn emit(&self, _type: u16, code: u16, value: i32) {
+            let event = libc::input_event {
+                time: libc::timeval {
+                    tv_sec: 0,
+                    tv_usec: 0,
+                },
+                type_: _type,
+                code,
+                value,
+            };
+            let size = std::mem::size_of::<libc::input_event>();
+            unsafe {
+                assert_eq!(
+                    size as isize,
+                    libc::write(self.fd, &event as *const _ as *const libc::c_void, size)
+                );
+            }
+        }
+    }
+
+    impl Drop for VirtualDevice {
+        fn drop(&mut self) {
+            unsafe {
+                libc::ioctl(self.fd, UI_DEV_DESTROY);
+                libc::close(self.fd);
+            }
+        }
+    }
+
+    /// Needs access to /dev/uinput and /dev/input, run with
+    /// `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn uinput_keys_and_rotation_are_read() {
+        const NAME: &str = "fb4rasp test keypad";
+
+        let uinput = VirtualDevice::create(NAME);
+        // Give udev time to create the device node
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        let device = evdev::enumerate()
+            .into_iter()
+            .find(|d| d.name().to_str() == Ok(NAME))
+            .expect("Virtual device not found");
+        let mut device = InputDevice::new(device);
+
+        uinput.emit(EV_KEY, KEY_ENTER, 1);
+        uinput.emit(EV_SYN, 0, 0);
+        uinput.emit(EV_KEY, KEY_ENTER, 0);
+        uinput.emit(EV_SYN, 0, 0);
+        uinput.emit(EV_REL, REL_DIAL, -2);
+        uinput.emit(EV_SYN, 0, 0);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let mapping = ScreenMapping {
+            calibration: TouchCalibration::default(),
+            rotation: Rotation::Rotate0,
+            width: 1.0,
+            height: 1.0,
+        };
+        let events: Vec<EventType> = device
+            .read_events(&mapping)
+            .into_iter()
+            .map(|e| e.what)
+            .collect();
+        assert_eq!(
+            vec![
+                EventType::KeyDown(KEY_ENTER),
+                EventType::KeyUp(KEY_ENTER),
+                EventType::Rotate(-2)
+            ],
+            events
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::Point;
+
+/// Clockwise rotation of the rendered content relative to the panel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees % 360 {
+            0 => Some(Rotation::Rotate0),
+            90 => Some(Rotation::Rotate90),
+            180 => Some(Rotation::Rotate180),
+            270 => Some(Rotation::Rotate270),
+            _ => None,
+        }
+    }
+
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+
+    /// Size as seen by the drawing code for a device of given size
+    pub fn size(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Sets up `context` so that drawing code can use rotated coordinates,
+    /// `width` and `height` are the size of the underlying device.
+    pub(crate) fn apply(self, context: &cairo::Context, width: f64, height: f64) {
+        match self {
+            Rotation::Rotate0 => (),
+            Rotation::Rotate90 => {
+                context.translate(width, 0.0);
+                context.rotate(std::f64::consts::FRAC_PI_2);
+            }
+            Rotation::Rotate180 => {
+                context.translate(width, height);
+                context.rotate(std::f64::consts::PI);
+            }
+            Rotation::Rotate270 => {
+                context.translate(0.0, height);
+                context.rotate(-std::f64::consts::FRAC_PI_2);
+            }
+        }
+    }
+
+    /// Maps a point in device coordinates (e.g. a touch) to rotated ones.
+    pub fn to_logical(self, pos: &Point, width: f64, height: f64) -> Point {
+        match self {
+            Rotation::Rotate0 => pos.clone(),
+            Rotation::Rotate90 => Point {
+                x: pos.y,
+                y: width - pos.x,
+            },
+            Rotation::Rotate180 => Point {
+                x: width - pos.x,
+                y: height - pos.y,
+            },
+            Rotation::Rotate270 => Point {
+                x: height - pos.y,
+                y: pos.x,
+            },
+        }
+    }
+
+    /// Device pixel shown at pixel (`x`, `y`) of the rotated content, `width`
+    /// and `height` are the size of the device.
+    pub(crate) fn to_device_pixel(
+        self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> (usize, usize) {
+        match self {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (width - 1 - y, x),
+            Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+            Rotation::Rotate270 => (y, height - 1 - x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_maps_device_corners() {
+        let (w, h) = (480.0, 320.0);
+        let top_left = |r: Rotation, x, y| {
+            let p = r.to_logical(&Point { x, y }, w, h);
+            (p.x, p.y)
+        };
+
+        assert_eq!((0.0, 0.0), top_left(Rotation::Rotate0, 0.0, 0.0));
+        assert_eq!((0.0, 0.0), top_left(Rotation::Rotate90, w, 0.0));
+        assert_eq!((0.0, 0.0), top_left(Rotation::Rotate180, w, h));
+        assert_eq!((0.0, 0.0), top_left(Rotation::Rotate270, 0.0, h));
+        assert_eq!((320.0, 480.0), top_left(Rotation::Rotate90, 0.0, h));
+        assert_eq!((479, 0), Rotation::Rotate90.to_device_pixel(0, 0, 480, 320));
+        assert_eq!(
+            (0, 319),
+            Rotation::Rotate270.to_device_pixel(0, 0, 480, 320)
+        );
+        assert_eq!(None, Rotation::from_degrees(45));
+        assert_eq!(Some(Rotation::Rotate270), Rotation::from_degrees(270));
+    }
+}
@@ -1,11 +1,71 @@
-use crate::{Color, Display, Event, Point, TextSize};
+use crate::{
+    draw,
+    fonts::{self, Fonts},
+    images::ImageCache,
+    Color, Display, Event, FontError, FontMetrics, Frame, ImageError, ImageStyle, Point, Rect,
+    Rotation, TextSize,
+};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Numbered frames are named `frame-000042.svg` etc.
+const FRAME_PREFIX: &str = "frame-";
+
+/// File format written by `CairoSvg`, PNG is rasterized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VectorFormat {
+    Svg,
+    Pdf,
+    Png,
+}
+
+impl VectorFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VectorFormat::Svg => "svg",
+            VectorFormat::Pdf => "pdf",
+            VectorFormat::Png => "png",
+        }
+    }
+}
+
+/// Where `CairoSvg` writes finished frames.
+#[derive(Debug, Clone)]
+pub struct VectorOutput {
+    pub format: VectorFormat,
+    /// File rewritten by every frame, or the directory of numbered frames
+    pub path: PathBuf,
+    /// Writes every frame into its own numbered file, only this many newest
+    /// ones are kept
+    pub frames: Option<usize>,
+}
+
+impl Default for VectorOutput {
+    fn default() -> Self {
+        Self {
+            format: VectorFormat::Svg,
+            path: PathBuf::from("output/test.svg"),
+            frames: None,
+        }
+    }
+}
 
 pub struct CairoSvg {
-    #[allow(dead_code)]
-    surface: Option<cairo::SvgSurface>,
+    /// Drawing is recorded and replayed into the output file when finished
+    surface: Option<cairo::RecordingSurface>,
+    /// Recording of the last finished frame
+    last: Option<cairo::RecordingSurface>,
     context: Option<cairo::Context>,
     width: usize,
     height: usize,
+    rotation: Rotation,
+    output: VectorOutput,
+    /// Number of the next frame, once known
+    frame: Option<u64>,
+    /// File of the frame being drawn
+    target: Option<PathBuf>,
+    images: ImageCache,
+    fonts: Fonts,
     started: bool,
 }
 
@@ -22,11 +82,11 @@ impl From<cairo::Error> for CairoSvgError {
 
 impl<'a> Display<'a> for CairoSvg {
     fn width(&self) -> usize {
-        self.width
+        self.rotation.size(self.width, self.height).0
     }
 
     fn height(&self) -> usize {
-        self.height
+        self.rotation.size(self.width, self.height).1
     }
 
     fn bytes_per_pixel(&self) -> usize {
@@ -35,21 +95,37 @@ impl<'a> Display<'a> for CairoSvg {
 
     fn clean(&mut self) {
         assert!(self.started());
+        let (width, height) = (self.width() as f64, self.height() as f64);
         let context = self.context.as_mut().unwrap();
-        context.rectangle(0.0, 0.0, self.width as f64, self.height as f64);
+        context.rectangle(0.0, 0.0, width, height);
         context.fill();
     }
 
     fn start(&mut self) {
-        self.surface = Some(
-            cairo::SvgSurface::new(
-                self.width() as f64,
-                self.height() as f64,
-                Some("output/test.svg"),
-            )
-            .unwrap(),
-        );
-        self.context = Some(cairo::Context::new(self.surface.as_ref().unwrap()));
+        let target = self.next_target();
+        if let Some(dir) = target.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                log::warn!("Failed to create output directory {:?}: {}", dir, e);
+            }
+        }
+
+        let surface = cairo::RecordingSurface::create(
+            cairo::Content::ColorAlpha,
+            cairo::Rectangle {
+                x: 0.0,
+                y: 0.0,
+                width: self.width as f64,
+                height: self.height as f64,
+            },
+        )
+        .unwrap();
+        let context = cairo::Context::new(&surface);
+        self.surface = Some(surface);
+        self.target = Some(target);
+        self.rotation
+            .apply(&context, self.width as f64, self.height as f64);
+        self.fonts.select(&context, fonts::DEFAULT_ROLE);
+        self.context = Some(context);
         self.started = true;
     }
 
@@ -66,11 +142,7 @@ impl<'a> Display<'a> for CairoSvg {
     fn text_size(&self, what: &str) -> TextSize {
         assert!(self.started());
         let context = self.context.as_ref().unwrap();
-        let extents = context.text_extents(what);
-        TextSize {
-            width: extents.width,
-            height: extents.height,
-        }
+        self.fonts.text_size(context, what)
     }
 
     fn render_text(&mut self, r#where: &Point, what: &str) -> Option<TextSize> {
@@ -81,20 +153,15 @@ impl<'a> Display<'a> for CairoSvg {
         assert!(self.started());
         let context = &mut self.context.as_mut().unwrap();
         context.move_to(r#where.x, r#where.y);
-        let extents = context.text_extents(what);
-        context.show_text(what);
-        Some(TextSize {
-            width: extents.width,
-            height: extents.height,
-        })
+        let size = self.fonts.text_size(context, what);
+        self.fonts.show_text(context, what);
+        Some(size)
     }
 
     fn set_font(&mut self, name: &str) {
         assert!(self.started());
         let context = &mut self.context.as_mut().unwrap();
-        let font =
-            cairo::FontFace::toy_create(name, cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-        context.set_font_face(&font);
+        self.fonts.select(context, name);
     }
 
     fn set_font_size(&mut self, size: f64) {
@@ -103,12 +170,135 @@ impl<'a> Display<'a> for CairoSvg {
         context.set_font_size(size);
     }
 
+    fn font_metrics(&self) -> FontMetrics {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        let extents = context.font_extents();
+        FontMetrics {
+            ascent: extents.ascent,
+            descent: extents.descent,
+            line_height: extents.height,
+        }
+    }
+
+    fn text_width(&self, what: &str) -> f64 {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.fonts.text_width(context, what)
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_line_width(width);
+    }
+
+    fn set_dash(&mut self, dashes: &[f64], offset: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.set_dash(dashes, offset);
+    }
+
+    fn draw_line(&mut self, from: &Point, to: &Point) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::line(context, from, to);
+    }
+
+    fn fill_rect(&mut self, rect: &Rect, radius: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::fill_rect(context, rect, radius);
+    }
+
+    fn stroke_rect(&mut self, rect: &Rect, radius: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::stroke_rect(context, rect, radius);
+    }
+
+    fn fill_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::fill_arc(context, center, radius, start, end);
+    }
+
+    fn stroke_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::stroke_arc(context, center, radius, start, end);
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        draw::clip(context, rect);
+    }
+
+    fn reset_clip(&mut self) {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        context.reset_clip();
+    }
+
+    fn load_image(&mut self, name: &str, path: &std::path::Path) -> Result<(), ImageError> {
+        self.images.load(name, path)
+    }
+
+    fn load_font(&mut self, role: &str, paths: &[std::path::PathBuf]) -> Result<(), FontError> {
+        self.fonts.load(role, paths)
+    }
+
+    fn image_size(&self, name: &str, style: &ImageStyle) -> Option<TextSize> {
+        self.images.size(name, style)
+    }
+
+    fn render_image(
+        &mut self,
+        r#where: &Point,
+        name: &str,
+        style: &ImageStyle,
+    ) -> Option<TextSize> {
+        assert!(self.started());
+        let context = self.context.as_ref().unwrap();
+        self.images.draw(context, name, r#where, style)
+    }
+
     fn finish(&mut self) {
-        self.surface = None;
         self.context = None;
+        if let (Some(surface), Some(target)) = (self.surface.take(), self.target.take()) {
+            if let Err(e) = self.write(&surface, &target) {
+                log::warn!("Failed to write frame to {:?}: {}", target, e);
+            }
+            self.last = Some(surface);
+        }
+        if let Some(keep) = self.output.frames {
+            self.remove_old_frames(keep);
+        }
         self.started = false;
     }
 
+    fn frame(&self) -> Option<Frame> {
+        let recording = self.last.as_ref()?;
+        let mut image = cairo::ImageSurface::create(
+            cairo::Format::ARgb32,
+            self.width as i32,
+            self.height as i32,
+        )
+        .ok()?;
+        replay(recording, &image);
+        let stride = image.get_stride() as usize;
+        let data = image.get_data().ok()?;
+        Frame::from_surface_data(
+            &data,
+            cairo_sys::FORMAT_A_RGB32,
+            stride,
+            self.width,
+            self.height,
+            self.rotation,
+        )
+    }
+
     fn init_events(&mut self) {}
 
     fn get_events(&mut self) -> Vec<Event> {
@@ -131,7 +321,155 @@ impl CairoSvg {
             context: None,
             width,
             height,
+            rotation: Rotation::Rotate0,
+            last: None,
+            output: VectorOutput::default(),
+            frame: None,
+            target: None,
+            images: ImageCache::default(),
+            fonts: Fonts::default(),
             started: false,
         })
     }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    /// Takes effect with the next frame, numbering of frames continues after
+    /// the ones already in the directory.
+    pub fn set_output(&mut self, output: VectorOutput) {
+        self.output = output;
+        self.frame = None;
+    }
+
+    /// Writes a recorded frame to `target` in the output format
+    fn write(
+        &self,
+        recording: &cairo::RecordingSurface,
+        target: &std::path::Path,
+    ) -> Result<(), String> {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let to_string = |e: cairo::Error| format!("{}", e);
+        let surface: cairo::Surface = match self.output.format {
+            VectorFormat::Svg => {
+                (*cairo::SvgSurface::new(width, height, Some(target)).map_err(to_string)?).clone()
+            }
+            VectorFormat::Pdf => {
+                (*cairo::PdfSurface::new(width, height, target).map_err(to_string)?).clone()
+            }
+            VectorFormat::Png => (*cairo::ImageSurface::create(
+                cairo::Format::ARgb32,
+                self.width as i32,
+                self.height as i32,
+            )
+            .map_err(to_string)?)
+            .clone(),
+        };
+        replay(recording, &surface);
+        if self.output.format == VectorFormat::Png {
+            save_png(surface.clone(), target)?;
+        }
+        // Vector surfaces are written out when finished
+        surface.finish();
+        Ok(())
+    }
+
+    fn next_target(&mut self) -> PathBuf {
+        if self.output.frames.is_none() {
+            return self.output.path.clone();
+        }
+
+        let frame = match self.frame {
+            Some(frame) => frame,
+            None => self.frame_numbers().last().map_or(0, |n| n + 1),
+        };
+        self.frame = Some(frame + 1);
+        self.frame_path(frame)
+    }
+
+    fn frame_path(&self, number: u64) -> PathBuf {
+        self.output.path.join(format!(
+            "{}{:06}.{}",
+            FRAME_PREFIX,
+            number,
+            self.output.format.extension()
+        ))
+    }
+
+    /// Numbers of frames in the output directory, in ascending order
+    fn frame_numbers(&self) -> Vec<u64> {
+        let suffix = format!(".{}", self.output.format.extension());
+        let entries = match std::fs::read_dir(&self.output.path) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let mut numbers: Vec<u64> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().into_string().ok()?;
+                name.strip_prefix(FRAME_PREFIX)?
+                    .strip_suffix(&suffix)?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        numbers.sort_unstable();
+        numbers
+    }
+
+    fn remove_old_frames(&self, keep: usize) {
+        let numbers = self.frame_numbers();
+        let old = numbers.len().saturating_sub(keep);
+        for number in numbers[..old].iter() {
+            let path = self.frame_path(*number);
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove old frame {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+fn replay(recording: &cairo::RecordingSurface, target: &cairo::Surface) {
+    let context = cairo::Context::new(target);
+    context.set_source_surface(recording, 0.0, 0.0);
+    context.paint();
+    target.flush();
+}
+
+fn save_png(surface: cairo::Surface, path: &std::path::Path) -> Result<(), String> {
+    let image = cairo::ImageSurface::try_from(surface).map_err(|_| "Not an image surface")?;
+    let mut file = std::fs::File::create(path).map_err(|e| format!("{}", e))?;
+    image.write_to_png(&mut file).map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_frames_are_removed() {
+        // Unique per process, so parallel test runs don't remove each other's
+        let dir = std::env::temp_dir().join(format!("fb4rasp_frames_{}", std::process::id()));
+
+        let mut svg = CairoSvg::new(8, 4).unwrap();
+        svg.set_output(VectorOutput {
+            format: VectorFormat::Png,
+            path: dir.clone(),
+            frames: Some(2),
+        });
+        for _ in 0..3 {
+            svg.start();
+            svg.clean();
+            svg.finish();
+        }
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(vec!["frame-000001.png", "frame-000002.png"], files);
+    }
 }
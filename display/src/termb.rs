@@ -0,0 +1,339 @@
+use crate::{
+    imgb::{CairoImage, CairoImageError},
+    Color, Display, Event, FontError, FontMetrics, Frame, ImageError, ImageStyle, Point, Rect,
+    TextSize,
+};
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+/// Used when the size of the terminal is unknown, e.g. output is redirected
+const DEFAULT_TERMINAL_SIZE: (usize, usize) = (80, 24);
+
+/// Renders into an in-memory surface like `CairoImage` and draws finished
+/// frames in the terminal, two pixels per character cell with Unicode half
+/// blocks and 24-bit ANSI colors. Frames are scaled down to fit the terminal
+/// and drawn from scratch when it is resized.
+pub struct CairoTerminal {
+    image: CairoImage,
+    /// Terminal size (columns, rows) of the last drawn frame
+    size: Option<(usize, usize)>,
+}
+
+impl<'a> Display<'a> for CairoTerminal {
+    fn width(&self) -> usize {
+        self.image.width()
+    }
+
+    fn height(&self) -> usize {
+        self.image.height()
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        self.image.bytes_per_pixel()
+    }
+
+    fn clean(&mut self) {
+        self.image.clean();
+    }
+
+    fn start(&mut self) {
+        self.image.start();
+    }
+
+    fn started(&self) -> bool {
+        self.image.started()
+    }
+
+    fn set_color(&mut self, color: &Color) {
+        self.image.set_color(color);
+    }
+
+    fn text_size(&self, what: &str) -> TextSize {
+        self.image.text_size(what)
+    }
+
+    fn render_text(&mut self, r#where: &Point, what: &str) -> Option<TextSize> {
+        self.image.render_text(r#where, what)
+    }
+
+    fn set_font(&mut self, name: &str) {
+        self.image.set_font(name);
+    }
+
+    fn set_font_size(&mut self, size: f64) {
+        self.image.set_font_size(size);
+    }
+
+    fn font_metrics(&self) -> FontMetrics {
+        self.image.font_metrics()
+    }
+
+    fn text_width(&self, what: &str) -> f64 {
+        self.image.text_width(what)
+    }
+
+    fn set_line_width(&mut self, width: f64) {
+        self.image.set_line_width(width);
+    }
+
+    fn set_dash(&mut self, dashes: &[f64], offset: f64) {
+        self.image.set_dash(dashes, offset);
+    }
+
+    fn draw_line(&mut self, from: &Point, to: &Point) {
+        self.image.draw_line(from, to);
+    }
+
+    fn fill_rect(&mut self, rect: &Rect, radius: f64) {
+        self.image.fill_rect(rect, radius);
+    }
+
+    fn stroke_rect(&mut self, rect: &Rect, radius: f64) {
+        self.image.stroke_rect(rect, radius);
+    }
+
+    fn fill_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        self.image.fill_arc(center, radius, start, end);
+    }
+
+    fn stroke_arc(&mut self, center: &Point, radius: f64, start: f64, end: f64) {
+        self.image.stroke_arc(center, radius, start, end);
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        self.image.clip(rect);
+    }
+
+    fn reset_clip(&mut self) {
+        self.image.reset_clip();
+    }
+
+    fn load_image(&mut self, name: &str, path: &std::path::Path) -> Result<(), ImageError> {
+        self.image.load_image(name, path)
+    }
+
+    fn load_font(&mut self, role: &str, paths: &[std::path::PathBuf]) -> Result<(), FontError> {
+        self.image.load_font(role, paths)
+    }
+
+    fn image_size(&self, name: &str, style: &ImageStyle) -> Option<TextSize> {
+        self.image.image_size(name, style)
+    }
+
+    fn render_image(
+        &mut self,
+        r#where: &Point,
+        name: &str,
+        style: &ImageStyle,
+    ) -> Option<TextSize> {
+        self.image.render_image(r#where, name, style)
+    }
+
+    fn finish(&mut self) {
+        self.image.finish();
+        self.redraw();
+    }
+
+    fn frame(&self) -> Option<Frame> {
+        self.image.frame()
+    }
+
+    fn init_events(&mut self) {}
+
+    fn get_events(&mut self) -> Vec<Event> {
+        vec![]
+    }
+
+    type DrawingBackend = plotters_cairo::CairoBackend<'a>;
+    type BackendError = plotters_cairo::CairoError;
+    fn get_backend(&'a self) -> Result<Self::DrawingBackend, Self::BackendError> {
+        self.image.get_backend()
+    }
+}
+
+impl Drop for CairoTerminal {
+    fn drop(&mut self) {
+        if self.size.is_some() {
+            // Cursor is shown again below the last frame
+            let mut stdout = std::io::stdout();
+            let _ = stdout
+                .write_all(b"\x1b[0m\x1b[?25h\n")
+                .and_then(|_| stdout.flush());
+        }
+    }
+}
+
+impl CairoTerminal {
+    /// Size of the surface frames are rendered into, in pixels.
+    pub fn new(width: usize, height: usize) -> Result<Self, CairoImageError> {
+        Ok(Self {
+            image: CairoImage::new(width, height)?,
+            size: None,
+        })
+    }
+
+    /// Draws the last finished frame again, the whole terminal is cleared
+    /// first if its size changed since the previous one.
+    pub fn redraw(&mut self) {
+        let size = terminal_size().unwrap_or(DEFAULT_TERMINAL_SIZE);
+        let mut out = String::new();
+        if self.size != Some(size) {
+            // Clears the screen and hides the cursor
+            out.push_str("\x1b[2J\x1b[?25l");
+            self.size = Some(size);
+        }
+        out.push_str(&self.ansi_frame(size.0, size.1));
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        if let Err(e) = stdout
+            .write_all(out.as_bytes())
+            .and_then(|_| stdout.flush())
+        {
+            log::warn!("Failed to draw frame in terminal: {}", e);
+        }
+    }
+
+    /// ANSI sequences drawing the last finished frame at the top left corner
+    /// of a terminal with given number of columns and rows.
+    fn ansi_frame(&self, columns: usize, rows: usize) -> String {
+        let (width, height) = self.image.device_size();
+        let scale = (columns as f64 / width as f64).min(2.0 * rows as f64 / height as f64);
+        let cells_x = ((width as f64 * scale).round() as usize).clamp(1, columns.max(1));
+        let pixels_y = ((height as f64 * scale).round() as usize).clamp(1, 2 * rows.max(1));
+
+        let pixels = self.downscale(cells_x, pixels_y);
+        let pixel = |x: usize, y: usize| pixels.get(y * cells_x + x).copied();
+
+        let mut out = String::from("\x1b[H");
+        for row in 0..pixels_y.div_ceil(2) {
+            if row > 0 {
+                out.push_str("\r\n");
+            }
+            let (mut fg, mut bg) = (None, None);
+            for x in 0..cells_x {
+                let top = pixel(x, 2 * row);
+                let bottom = pixel(x, 2 * row + 1);
+                if top != fg {
+                    if let Some([r, g, b]) = top {
+                        let _ = write!(out, "\x1b[38;2;{};{};{}m", r, g, b);
+                    }
+                    fg = top;
+                }
+                if bottom != bg {
+                    match bottom {
+                        Some([r, g, b]) => {
+                            let _ = write!(out, "\x1b[48;2;{};{};{}m", r, g, b);
+                        }
+                        None => out.push_str("\x1b[49m"),
+                    }
+                    bg = bottom;
+                }
+                out.push('\u{2580}');
+            }
+            // Rest of the line is cleared, e.g. after the terminal got wider
+            out.push_str("\x1b[0m\x1b[K");
+        }
+        // Rows below the frame are cleared as well
+        out.push_str("\x1b[0m\x1b[J");
+        out
+    }
+
+    /// RGB colors of the frame scaled down to `width` x `height`, each one
+    /// the average of the pixels it covers. Pixels are composed over black.
+    fn downscale(&self, width: usize, height: usize) -> Vec<[u8; 3]> {
+        let (source_width, source_height) = self.image.device_size();
+        let stride = self.image.stride();
+        let mut pixels = Vec::with_capacity(width * height);
+        let result = self.image.with_data(|data| {
+            for y in 0..height {
+                let (y0, y1) = span(y, height, source_height);
+                for x in 0..width {
+                    let (x0, x1) = span(x, width, source_width);
+                    let mut sum = [0u32; 3];
+                    for sy in y0..y1 {
+                        for sx in x0..x1 {
+                            let offset = sy * stride + sx * 4;
+                            let mut bytes = [0u8; 4];
+                            bytes.copy_from_slice(&data[offset..offset + 4]);
+                            // Premultiplied, i.e. already composed over black
+                            let argb = u32::from_ne_bytes(bytes);
+                            sum[0] += (argb >> 16) & 0xff;
+                            sum[1] += (argb >> 8) & 0xff;
+                            sum[2] += argb & 0xff;
+                        }
+                    }
+                    let count = ((x1 - x0) * (y1 - y0)).max(1) as u32;
+                    pixels.push([
+                        (sum[0] / count) as u8,
+                        (sum[1] / count) as u8,
+                        (sum[2] / count) as u8,
+                    ]);
+                }
+            }
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to read frame: {:?}", e);
+        }
+        pixels
+    }
+}
+
+/// Range of source pixels covered by target pixel `index`, never empty
+fn span(index: usize, target: usize, source: usize) -> (usize, usize) {
+    let start = index * source / target;
+    let end = ((index + 1) * source / target).max(start + 1).min(source);
+    (start.min(end - 1), end)
+}
+
+/// Columns and rows of the terminal on standard output, if there is one
+fn terminal_size() -> Option<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result != 0 || size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+
+    Some((size.ws_col as usize, size.ws_row as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_is_drawn_with_half_blocks() {
+        let mut term = CairoTerminal::new(8, 4).unwrap();
+        term.image.start();
+        term.image.set_color(&Color {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+            alpha: 1.0,
+        });
+        term.image.clean();
+        term.image.set_color(&Color {
+            red: 0.0,
+            green: 0.0,
+            blue: 1.0,
+            alpha: 1.0,
+        });
+        term.image.fill_rect(
+            &Rect {
+                x: 0.0,
+                y: 2.0,
+                width: 8.0,
+                height: 2.0,
+            },
+            0.0,
+        );
+        term.image.finish();
+
+        // 8x4 pixels are scaled to 4x2, a single row of cells
+        let frame = term.ansi_frame(4, 10);
+        assert_eq!(
+            "\x1b[H\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m\u{2580}\u{2580}\u{2580}\u{2580}\x1b[0m\x1b[K\x1b[0m\x1b[J",
+            frame
+        );
+    }
+}
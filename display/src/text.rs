@@ -0,0 +1,273 @@
+use crate::{Point, Rect};
+
+const ELLIPSIS: &str = "…";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// How text is placed in a box, lines are always separated by `\n`.
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub align: Align,
+    /// Breaks lines on word boundaries (or anywhere if a word doesn't fit)
+    pub wrap: bool,
+    /// Shortens lines wider than the box, as well as the last one if there
+    /// are more lines than fit into it
+    pub ellipsis: bool,
+    /// Multiplies distance between baselines of lines
+    pub line_spacing: f64,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            align: Align::Left,
+            wrap: false,
+            ellipsis: false,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// Metrics of the current font, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    /// Distance from the baseline to the top of the highest glyphs
+    pub ascent: f64,
+    /// Distance from the baseline to the bottom of the lowest glyphs
+    pub descent: f64,
+    /// Recommended distance between baselines of consecutive lines
+    pub line_height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub text: String,
+    /// Start of the baseline, i.e. where `render_text` draws the line
+    pub baseline: Point,
+    /// Horizontal advance of the text
+    pub width: f64,
+    pub ascent: f64,
+    pub descent: f64,
+}
+
+impl TextLine {
+    pub fn top(&self) -> f64 {
+        self.baseline.y - self.ascent
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.baseline.y + self.descent
+    }
+}
+
+/// Splits `text` into lines placed in `rect`, `measure` returns horizontal
+/// advance of a string in the current font.
+pub fn layout<F: Fn(&str) -> f64>(
+    text: &str,
+    rect: &Rect,
+    style: &TextLayout,
+    metrics: &FontMetrics,
+    measure: F,
+) -> Vec<TextLine> {
+    let mut lines: Vec<String> = vec![];
+    for paragraph in text.split('\n') {
+        if style.wrap {
+            lines.extend(wrap(paragraph, rect.width, &measure));
+        } else {
+            lines.push(paragraph.to_owned());
+        }
+    }
+
+    // At least one line is kept, even if the box is too low for it
+    let line_height = metrics.line_height * style.line_spacing;
+    let fitting = if line_height > 0.0 {
+        ((rect.height - metrics.ascent - metrics.descent) / line_height).floor() as i64 + 1
+    } else {
+        lines.len() as i64
+    };
+    let fitting = fitting.max(1) as usize;
+    let truncated = lines.len() > fitting;
+    lines.truncate(fitting);
+
+    let last = lines.len() - 1;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let text = if style.ellipsis {
+                ellipsize(&line, rect.width, truncated && i == last, &measure)
+            } else {
+                line
+            };
+            let width = measure(&text);
+            let x = match style.align {
+                Align::Left => rect.x,
+                Align::Center => rect.x + (rect.width - width) / 2.0,
+                Align::Right => rect.x + rect.width - width,
+            };
+            TextLine {
+                text,
+                baseline: Point {
+                    x,
+                    y: rect.y + metrics.ascent + i as f64 * line_height,
+                },
+                width,
+                ascent: metrics.ascent,
+                descent: metrics.descent,
+            }
+        })
+        .collect()
+}
+
+/// Greedy word wrap, words wider than `width` are broken anywhere.
+fn wrap<F: Fn(&str) -> f64>(paragraph: &str, width: f64, measure: &F) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in paragraph.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_owned()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if measure(&candidate) <= width {
+            line = candidate;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            line.push(c);
+            if measure(&line) > width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, c.to_string()));
+            }
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Shortens `text` to fit into `width` with an ellipsis, `forced` adds the
+/// ellipsis even if it fits as it is.
+fn ellipsize<F: Fn(&str) -> f64>(text: &str, width: f64, forced: bool, measure: &F) -> String {
+    if !forced && measure(text) <= width {
+        return text.to_owned();
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    loop {
+        let shortened = chars.iter().collect::<String>();
+        let candidate = format!("{}{}", shortened.trim_end(), ELLIPSIS);
+        if measure(&candidate) <= width {
+            return candidate;
+        }
+        if chars.pop().is_none() {
+            return String::new();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: FontMetrics = FontMetrics {
+        ascent: 8.0,
+        descent: 2.0,
+        line_height: 12.0,
+    };
+
+    fn measure(s: &str) -> f64 {
+        s.chars().count() as f64 * 10.0
+    }
+
+    fn texts(text: &str, width: f64, height: f64, style: &TextLayout) -> Vec<String> {
+        let rect = Rect {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        };
+        layout(text, &rect, style, &METRICS, measure)
+            .into_iter()
+            .map(|l| l.text)
+            .collect()
+    }
+
+    #[test]
+    fn text_is_wrapped_and_ellipsized() {
+        let wrap = TextLayout {
+            wrap: true,
+            ..TextLayout::default()
+        };
+        assert_eq!(
+            vec!["one two", "three", "four"],
+            texts("one two three four", 70.0, 100.0, &wrap)
+        );
+        assert_eq!(
+            vec!["abc", "def", "g"],
+            texts("abcdefg", 30.0, 100.0, &wrap)
+        );
+        assert_eq!(vec!["a", "", "b"], texts("a\n\nb", 30.0, 100.0, &wrap));
+
+        let ellipsis = TextLayout {
+            ellipsis: true,
+            ..TextLayout::default()
+        };
+        assert_eq!(
+            vec!["my-long…"],
+            texts("my-long-hostname", 80.0, 10.0, &ellipsis)
+        );
+        assert_eq!(vec!["short"], texts("short", 80.0, 10.0, &ellipsis));
+        assert_eq!(vec!["…"], texts("hostname", 10.0, 10.0, &ellipsis));
+
+        // Two lines fit into 22 pixels
+        let both = TextLayout {
+            wrap: true,
+            ellipsis: true,
+            ..TextLayout::default()
+        };
+        assert_eq!(
+            vec!["one two", "three…"],
+            texts("one two three four", 70.0, 22.0, &both)
+        );
+        assert_eq!(
+            vec!["one two", "three", "four"],
+            texts("one two three four", 70.0, 34.0, &both)
+        );
+    }
+
+    #[test]
+    fn lines_are_aligned() {
+        let rect = Rect {
+            x: 10.0,
+            y: 20.0,
+            width: 100.0,
+            height: 100.0,
+        };
+        let style = |align| TextLayout {
+            align,
+            ..TextLayout::default()
+        };
+
+        let lines = layout("ab\nabcd", &rect, &style(Align::Right), &METRICS, measure);
+        assert_eq!(Point { x: 90.0, y: 28.0 }, lines[0].baseline);
+        assert_eq!(Point { x: 70.0, y: 40.0 }, lines[1].baseline);
+        assert_eq!((32.0, 42.0), (lines[1].top(), lines[1].bottom()));
+
+        let lines = layout("ab", &rect, &style(Align::Center), &METRICS, measure);
+        assert_eq!(50.0, lines[0].baseline.x);
+        assert_eq!(20.0, lines[0].width);
+    }
+}
@@ -0,0 +1,583 @@
+use crate::{Event, EventType, Frame, Point};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{tcp::OwnedReadHalf, TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+
+/// Side of the squares frames are compared in, changed ones are sent
+const TILE_SIZE: usize = 16;
+
+const SECURITY_NONE: u8 = 1;
+const ENCODING_RAW: i32 = 0;
+
+/// Serves frames over RFB (the VNC protocol) without any authentication, so
+/// it should listen on loopback or a trusted network only. Presses of the
+/// pointer button become `Touched` events.
+pub struct VncServer {
+    frames: watch::Sender<Option<Arc<Frame>>>,
+    input: Option<VncInput>,
+    local_addr: SocketAddr,
+}
+
+/// Pointer events of all clients of a `VncServer`
+pub struct VncInput {
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+impl VncServer {
+    /// Starts listening on `addr`, clients see `name` as the desktop name.
+    pub async fn bind(addr: SocketAddr, name: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (frames, receiver) = watch::channel(None);
+        let (sender, events) = mpsc::unbounded_channel();
+        tokio::spawn(accept(listener, name.to_owned(), receiver, sender));
+
+        Ok(Self {
+            frames,
+            input: Some(VncInput { events }),
+            local_addr,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Pointer events can be taken only once
+    pub fn take_input(&mut self) -> Option<VncInput> {
+        self.input.take()
+    }
+
+    /// Sends changed parts of `frame` to clients waiting for an update.
+    pub fn publish(&self, frame: Frame) {
+        // Fails only without receivers, the listener keeps one
+        let _ = self.frames.send(Some(Arc::new(frame)));
+    }
+}
+
+impl VncInput {
+    /// Waits for events, `None` once the server is gone
+    pub async fn next_events(&mut self) -> Option<Vec<Event>> {
+        let mut events = vec![self.events.recv().await?];
+        while let Ok(e) = self.events.try_recv() {
+            events.push(e);
+        }
+        Some(events)
+    }
+}
+
+async fn accept(
+    listener: TcpListener,
+    name: String,
+    frames: watch::Receiver<Option<Arc<Frame>>>,
+    events: mpsc::UnboundedSender<Event>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, address)) => {
+                log::info!("VNC client {} connected", address);
+                let (name, frames, events) = (name.clone(), frames.clone(), events.clone());
+                tokio::spawn(async move {
+                    match serve(stream, name, frames, events).await {
+                        Ok(()) => log::info!("VNC client {} disconnected", address),
+                        Err(e) => log::info!("VNC client {} disconnected: {}", address, e),
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to accept VNC client: {}", e),
+        }
+    }
+}
+
+/// Part of a frame in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Area {
+    fn intersect(&self, other: &Area) -> Option<Area> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        if right <= x || bottom <= y {
+            return None;
+        }
+
+        Some(Area {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+}
+
+/// How a client wants pixels to be encoded, only true colour is supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PixelFormat {
+    bits_per_pixel: u8,
+    depth: u8,
+    big_endian: bool,
+    true_colour: bool,
+    /// Red, green and blue
+    max: [u16; 3],
+    shift: [u8; 3],
+}
+
+impl PixelFormat {
+    /// Pixels of frames, little endian
+    const SERVER: PixelFormat = PixelFormat {
+        bits_per_pixel: 32,
+        depth: 24,
+        big_endian: false,
+        true_colour: true,
+        max: [255, 255, 255],
+        shift: [16, 8, 0],
+    };
+
+    fn parse(bytes: &[u8]) -> Self {
+        let max = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        Self {
+            bits_per_pixel: bytes[0],
+            depth: bytes[1],
+            big_endian: bytes[2] != 0,
+            true_colour: bytes[3] != 0,
+            max: [max(4), max(6), max(8)],
+            shift: [bytes[10], bytes[11], bytes[12]],
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[0] = self.bits_per_pixel;
+        bytes[1] = self.depth;
+        bytes[2] = self.big_endian as u8;
+        bytes[3] = self.true_colour as u8;
+        for (i, max) in self.max.iter().enumerate() {
+            bytes[4 + 2 * i..6 + 2 * i].copy_from_slice(&max.to_be_bytes());
+        }
+        bytes[10..13].copy_from_slice(&self.shift);
+        bytes
+    }
+
+    fn is_supported(&self) -> bool {
+        self.true_colour && matches!(self.bits_per_pixel, 8 | 16 | 32)
+    }
+
+    fn encode(&self, rgb: u32, out: &mut Vec<u8>) {
+        let mut value = 0u32;
+        for (i, channel_shift) in [16, 8, 0].iter().enumerate() {
+            let channel = (rgb >> channel_shift) & 0xff;
+            value |= (channel * self.max[i] as u32 / 255) << self.shift[i];
+        }
+
+        let size = self.bits_per_pixel as usize / 8;
+        if self.big_endian {
+            out.extend_from_slice(&value.to_be_bytes()[4 - size..]);
+        } else {
+            out.extend_from_slice(&value.to_le_bytes()[..size]);
+        }
+    }
+}
+
+/// Messages of a client the connection has to react to
+enum ClientMessage {
+    SetPixelFormat(PixelFormat),
+    UpdateRequest { incremental: bool, area: Area },
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+async fn serve(
+    stream: TcpStream,
+    name: String,
+    mut frames: watch::Receiver<Option<Arc<Frame>>>,
+    events: mpsc::UnboundedSender<Event>,
+) -> std::io::Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+
+    writer.write_all(b"RFB 003.008\n").await?;
+    let mut version = [0u8; 12];
+    reader.read_exact(&mut version).await?;
+    let minor = match &version {
+        b"RFB 003.003\n" => 3,
+        b"RFB 003.007\n" => 7,
+        v if v.starts_with(b"RFB 003.") => 8,
+        v => {
+            return Err(invalid_data(format!(
+                "Unsupported protocol version {:?}",
+                String::from_utf8_lossy(v)
+            )))
+        }
+    };
+    if minor == 3 {
+        writer.write_u32(SECURITY_NONE as u32).await?;
+    } else {
+        writer.write_all(&[1, SECURITY_NONE]).await?;
+        let security = reader.read_u8().await?;
+        if security != SECURITY_NONE {
+            return Err(invalid_data(format!("Unsupported security {}", security)));
+        }
+        if minor >= 8 {
+            writer.write_u32(0).await?;
+        }
+    }
+    // Shared flag, clients always share the display
+    reader.read_u8().await?;
+
+    while frames.borrow().is_none() {
+        if frames.changed().await.is_err() {
+            return Ok(());
+        }
+    }
+    let (width, height) = {
+        let frame = frames.borrow();
+        let frame = frame.as_ref().unwrap();
+        (frame.width, frame.height)
+    };
+    let screen = Area {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+
+    let mut init = Vec::new();
+    init.extend_from_slice(&(width as u16).to_be_bytes());
+    init.extend_from_slice(&(height as u16).to_be_bytes());
+    init.extend_from_slice(&PixelFormat::SERVER.to_bytes());
+    init.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    init.extend_from_slice(name.as_bytes());
+    writer.write_all(&init).await?;
+
+    let (sender, mut messages) = mpsc::unbounded_channel();
+    let reading = tokio::spawn(read_messages(reader, sender, events));
+
+    let mut format = PixelFormat::SERVER;
+    // Frame the client has, changes to it are sent on incremental requests
+    let mut sent: Option<Arc<Frame>> = None;
+    let mut requested: Option<Area> = None;
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Some(ClientMessage::SetPixelFormat(f)) => {
+                    format = f;
+                    sent = None;
+                }
+                Some(ClientMessage::UpdateRequest { incremental, area }) => {
+                    if !incremental {
+                        sent = None;
+                    }
+                    requested = area.intersect(&screen);
+                }
+                None => break,
+            },
+            changed = frames.changed(), if requested.is_some() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+
+        let area = match requested {
+            Some(area) => area,
+            None => continue,
+        };
+        let frame = match frames.borrow_and_update().clone() {
+            Some(frame) => frame,
+            None => continue,
+        };
+        let areas: Vec<_> = match &sent {
+            Some(previous) => dirty_areas(previous, &frame),
+            None => vec![screen],
+        }
+        .iter()
+        .filter_map(|a| a.intersect(&area))
+        .filter_map(|a| {
+            a.intersect(&Area {
+                x: 0,
+                y: 0,
+                width: frame.width,
+                height: frame.height,
+            })
+        })
+        .collect();
+        if areas.is_empty() {
+            continue;
+        }
+
+        writer.write_all(&update(&frame, &areas, &format)).await?;
+        sent = Some(frame);
+        requested = None;
+    }
+
+    reading.abort();
+    Ok(())
+}
+
+/// FramebufferUpdate message with raw pixels of `areas`
+fn update(frame: &Frame, areas: &[Area], format: &PixelFormat) -> Vec<u8> {
+    let mut message = vec![0, 0];
+    message.extend_from_slice(&(areas.len() as u16).to_be_bytes());
+    for area in areas.iter() {
+        for value in [area.x, area.y, area.width, area.height].iter() {
+            message.extend_from_slice(&(*value as u16).to_be_bytes());
+        }
+        message.extend_from_slice(&ENCODING_RAW.to_be_bytes());
+        for y in area.y..area.y + area.height {
+            let row = &frame.pixels[y * frame.width..(y + 1) * frame.width];
+            for pixel in row[area.x..area.x + area.width].iter() {
+                format.encode(*pixel, &mut message);
+            }
+        }
+    }
+    message
+}
+
+/// Parts of `frame` which differ from `previous`, changed tiles next to each
+/// other in a row are merged.
+fn dirty_areas(previous: &Frame, frame: &Frame) -> Vec<Area> {
+    if previous.width != frame.width || previous.height != frame.height {
+        return vec![Area {
+            x: 0,
+            y: 0,
+            width: frame.width,
+            height: frame.height,
+        }];
+    }
+
+    let tile_changed = |tx: usize, ty: usize| {
+        let (x, width) = (tx * TILE_SIZE, TILE_SIZE.min(frame.width - tx * TILE_SIZE));
+        (ty * TILE_SIZE..(ty * TILE_SIZE + TILE_SIZE).min(frame.height)).any(|y| {
+            let start = y * frame.width + x;
+            frame.pixels[start..start + width] != previous.pixels[start..start + width]
+        })
+    };
+
+    let mut areas = Vec::new();
+    for ty in 0..frame.height.div_ceil(TILE_SIZE) {
+        let mut run: Option<usize> = None;
+        let tiles = frame.width.div_ceil(TILE_SIZE);
+        for tx in 0..=tiles {
+            let changed = tx < tiles && tile_changed(tx, ty);
+            match (run, changed) {
+                (None, true) => run = Some(tx),
+                (Some(start), false) => {
+                    let x = start * TILE_SIZE;
+                    let y = ty * TILE_SIZE;
+                    areas.push(Area {
+                        x,
+                        y,
+                        width: (tx * TILE_SIZE).min(frame.width) - x,
+                        height: TILE_SIZE.min(frame.height - y),
+                    });
+                    run = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    areas
+}
+
+async fn skip(reader: &mut OwnedReadHalf, count: u64) -> std::io::Result<()> {
+    let skipped = tokio::io::copy(&mut reader.take(count), &mut tokio::io::sink()).await?;
+    if skipped < count {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Parses messages of a client, pointer presses are sent as events directly.
+async fn read_messages(
+    mut reader: OwnedReadHalf,
+    messages: mpsc::UnboundedSender<ClientMessage>,
+    events: mpsc::UnboundedSender<Event>,
+) -> std::io::Result<()> {
+    let mut buttons = 0u8;
+    loop {
+        let message = match reader.read_u8().await? {
+            0 => {
+                let mut bytes = [0u8; 19];
+                reader.read_exact(&mut bytes).await?;
+                let format = PixelFormat::parse(&bytes[3..]);
+                if !format.is_supported() {
+                    log::warn!("Unsupported VNC pixel format {:?}", format);
+                    continue;
+                }
+                ClientMessage::SetPixelFormat(format)
+            }
+            2 => {
+                // Only raw encoding is used, which every client supports
+                reader.read_u8().await?;
+                let count = reader.read_u16().await?;
+                skip(&mut reader, 4 * count as u64).await?;
+                continue;
+            }
+            3 => {
+                let incremental = reader.read_u8().await? != 0;
+                let mut area = [0usize; 4];
+                for value in area.iter_mut() {
+                    *value = reader.read_u16().await? as usize;
+                }
+                ClientMessage::UpdateRequest {
+                    incremental,
+                    area: Area {
+                        x: area[0],
+                        y: area[1],
+                        width: area[2],
+                        height: area[3],
+                    },
+                }
+            }
+            // Keys
+            4 => {
+                skip(&mut reader, 7).await?;
+                continue;
+            }
+            5 => {
+                let mask = reader.read_u8().await?;
+                let x = reader.read_u16().await?;
+                let y = reader.read_u16().await?;
+                if mask & 1 != 0 && buttons & 1 == 0 {
+                    let _ = events.send(Event {
+                        what: EventType::Touched,
+                        position: Point {
+                            x: x as f64,
+                            y: y as f64,
+                        },
+                    });
+                }
+                buttons = mask;
+                continue;
+            }
+            // Clipboard
+            6 => {
+                skip(&mut reader, 3).await?;
+                let length = reader.read_u32().await?;
+                skip(&mut reader, length as u64).await?;
+                continue;
+            }
+            kind => return Err(invalid_data(format!("Unknown message type {}", kind))),
+        };
+
+        if messages.send(message).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: usize, height: usize) -> Frame {
+        Frame {
+            width,
+            height,
+            pixels: vec![0x00_20_40_80; width * height],
+        }
+    }
+
+    #[test]
+    fn changed_tiles_are_merged_in_rows() {
+        let previous = frame(40, 20);
+        let mut current = previous.clone();
+        current.pixels[3 * 40 + 5] = 0;
+        current.pixels[3 * 40 + 20] = 0;
+        current.pixels[18 * 40 + 39] = 0;
+
+        assert_eq!(
+            vec![
+                Area {
+                    x: 0,
+                    y: 0,
+                    width: 32,
+                    height: 16
+                },
+                Area {
+                    x: 32,
+                    y: 16,
+                    width: 8,
+                    height: 4
+                },
+            ],
+            dirty_areas(&previous, &current)
+        );
+        assert!(dirty_areas(&previous, &previous).is_empty());
+    }
+
+    #[tokio::test]
+    async fn client_gets_updates_and_sends_touches() {
+        let mut server = VncServer::bind("127.0.0.1:0".parse().unwrap(), "fb4rasp")
+            .await
+            .unwrap();
+        let mut input = server.take_input().unwrap();
+        server.publish(frame(4, 2));
+
+        let mut client = TcpStream::connect(server.local_addr()).await.unwrap();
+        let mut version = [0u8; 12];
+        client.read_exact(&mut version).await.unwrap();
+        assert_eq!(b"RFB 003.008\n", &version);
+        client.write_all(b"RFB 003.008\n").await.unwrap();
+        let mut security = [0u8; 2];
+        client.read_exact(&mut security).await.unwrap();
+        assert_eq!([1, SECURITY_NONE], security);
+        client.write_all(&[SECURITY_NONE]).await.unwrap();
+        assert_eq!(0, client.read_u32().await.unwrap());
+        client.write_all(&[1]).await.unwrap();
+
+        let mut init = [0u8; 2 + 2 + 16 + 4 + 7];
+        client.read_exact(&mut init).await.unwrap();
+        assert_eq!([0, 4, 0, 2], init[..4]);
+        assert_eq!(b"fb4rasp", &init[24..]);
+
+        // Whole frame is sent first
+        client
+            .write_all(&[3, 0, 0, 0, 0, 0, 0, 4, 0, 2])
+            .await
+            .unwrap();
+        let mut update = [0u8; 4 + 12 + 4 * 2 * 4];
+        client.read_exact(&mut update).await.unwrap();
+        assert_eq!([0, 0, 0, 1, 0, 0, 0, 0, 0, 4, 0, 2], update[..12]);
+        assert_eq!([0x80, 0x40, 0x20, 0], update[16..20]);
+
+        // Then only changes, in the pixel format of the client
+        let mut format = PixelFormat::SERVER;
+        format.big_endian = true;
+        let mut message = vec![0, 0, 0, 0];
+        message.extend_from_slice(&format.to_bytes());
+        client.write_all(&message).await.unwrap();
+        client
+            .write_all(&[3, 0, 0, 0, 0, 0, 0, 4, 0, 2])
+            .await
+            .unwrap();
+        client.read_exact(&mut update).await.unwrap();
+        client
+            .write_all(&[3, 1, 0, 0, 0, 0, 0, 4, 0, 2])
+            .await
+            .unwrap();
+        let mut changed = frame(4, 2);
+        changed.pixels[0] = 0x00_ff_00_00;
+        server.publish(changed);
+        client.read_exact(&mut update).await.unwrap();
+        assert_eq!([0, 0xff, 0, 0], update[16..20]);
+
+        client.write_all(&[5, 1, 0, 3, 0, 1]).await.unwrap();
+        client.write_all(&[5, 1, 0, 2, 0, 1]).await.unwrap();
+        client.write_all(&[5, 0, 0, 2, 0, 1]).await.unwrap();
+        let events = input.next_events().await.unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!(EventType::Touched, events[0].what);
+        assert_eq!(Point { x: 3.0, y: 1.0 }, events[0].position);
+    }
+}
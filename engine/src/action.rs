@@ -1,4 +1,4 @@
-use crate::params::Parameters;
+use crate::params::{Layout, Parameters};
 
 pub trait Action {
     fn apply(&self, params: &mut Parameters) -> bool;
@@ -14,3 +14,108 @@ impl Action for ShutdownAction {
         true
     }
 }
+
+pub struct ToggleLayoutAction {}
+
+impl Action for ToggleLayoutAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        match params.options.main_layout {
+            Layout::Vertical => params.options.main_layout = Layout::Horizontal,
+            Layout::Horizontal => params.options.main_layout = Layout::Vertical,
+        }
+        true
+    }
+}
+
+pub struct NextPageAction {}
+
+impl Action for NextPageAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.pages.show_next()
+    }
+}
+
+pub struct PreviousPageAction {}
+
+impl Action for PreviousPageAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.pages.show_previous()
+    }
+}
+
+/// Keeps the carousel on the current page, or lets it continue again
+pub struct PinPageAction {}
+
+impl Action for PinPageAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.pages.toggle_pin()
+    }
+}
+
+/// Returns to the first page, which isn't pinned
+pub struct FirstPageAction {}
+
+impl Action for FirstPageAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.pages.show_first()
+    }
+}
+
+pub struct GotoPageAction {
+    page: String,
+}
+
+impl GotoPageAction {
+    pub fn new(page: &str) -> Self {
+        Self {
+            page: page.to_owned(),
+        }
+    }
+}
+
+impl Action for GotoPageAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.pages.show(&self.page)
+    }
+}
+
+pub struct ThemeAction {
+    theme: String,
+}
+
+impl ThemeAction {
+    pub fn new(theme: &str) -> Self {
+        Self {
+            theme: theme.to_owned(),
+        }
+    }
+}
+
+impl Action for ThemeAction {
+    fn apply(&self, params: &mut Parameters) -> bool {
+        params.options.theme = Some(self.theme.clone());
+        true
+    }
+}
+
+/// Creates an action by its name as used in the config file, `page:<name>`
+/// shows the page and `theme:<name>` switches to the theme with given name.
+pub fn from_name(name: &str) -> Option<Box<dyn Action + Send>> {
+    if let Some(page) = name.strip_prefix("page:") {
+        return Some(Box::new(GotoPageAction::new(page)));
+    }
+    if let Some(theme) = name.strip_prefix("theme:") {
+        return Some(Box::new(ThemeAction::new(theme)));
+    }
+
+    let action: Box<dyn Action + Send> = match name {
+        "previous" => Box::new(PreviousPageAction {}),
+        "next" => Box::new(NextPageAction {}),
+        "select" => Box::new(PinPageAction {}),
+        "back" => Box::new(FirstPageAction {}),
+        "layout" => Box::new(ToggleLayoutAction {}),
+        "shutdown" => Box::new(ShutdownAction {}),
+        _ => return None,
+    };
+    Some(action)
+}
@@ -0,0 +1,150 @@
+use crate::pages::Pages;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Page shown by the carousel and for how long.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarouselPage {
+    pub name: String,
+    pub dwell: Duration,
+}
+
+/// Rotates through pages while nobody is using the display, user input
+/// pauses it for a while.
+#[derive(Debug, Clone)]
+pub struct Carousel {
+    /// Pages in the order they are shown, all pages if empty
+    order: Vec<CarouselPage>,
+    /// Used for pages without their own dwell time
+    dwell: Duration,
+    /// Time without rotation after user input
+    pause: Duration,
+    deadline: Instant,
+}
+
+impl Carousel {
+    pub fn new(order: Vec<CarouselPage>, dwell: Duration, pause: Duration) -> Self {
+        Self {
+            order,
+            dwell,
+            pause,
+            deadline: Instant::now() + dwell,
+        }
+    }
+
+    /// When the next page should be shown
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Shows the current page for its whole dwell time, starting at `now`.
+    pub fn restart(&mut self, pages: &Pages, now: Instant) {
+        let current = pages.current_name().unwrap_or("");
+        let dwell = self
+            .pages(pages)
+            .into_iter()
+            .find(|p| p.name == current)
+            .map_or(self.dwell, |p| p.dwell);
+        self.deadline = now + dwell;
+    }
+
+    /// Postpones rotation for the pause period after user input at `now`.
+    pub fn interrupt(&mut self, now: Instant) {
+        self.deadline = self.deadline.max(now + self.pause);
+    }
+
+    /// Shows the page following the current one in the carousel order, or the
+    /// first one if the current page isn't part of it. A pinned page is kept.
+    /// Returns whether the page changed.
+    pub fn rotate(&mut self, pages: &mut Pages, now: Instant) -> bool {
+        if pages.pinned() {
+            self.restart(pages, now);
+            return false;
+        }
+
+        let order = self.pages(pages);
+        if order.is_empty() {
+            self.deadline = now + self.dwell;
+            return false;
+        }
+
+        let next = order
+            .iter()
+            .position(|p| Some(p.name.as_str()) == pages.current_name())
+            .map_or(0, |i| (i + 1) % order.len());
+        self.deadline = now + order[next].dwell;
+        let previous = pages.current();
+        pages.show(&order[next].name) && pages.current() != previous
+    }
+
+    /// Pages of the carousel which exist
+    fn pages(&self, pages: &Pages) -> Vec<CarouselPage> {
+        if self.order.is_empty() {
+            return pages
+                .names()
+                .iter()
+                .map(|name| CarouselPage {
+                    name: name.clone(),
+                    dwell: self.dwell,
+                })
+                .collect();
+        }
+
+        self.order
+            .iter()
+            .filter(|p| pages.names().contains(&p.name))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_rotated_in_order() {
+        let secs = Duration::from_secs;
+        let mut pages = Pages::new(vec!["overview".into(), "laptop".into(), "network".into()]);
+        let order = vec![
+            CarouselPage {
+                name: "network".into(),
+                dwell: secs(5),
+            },
+            CarouselPage {
+                name: "alerts".into(),
+                dwell: secs(5),
+            },
+            CarouselPage {
+                name: "overview".into(),
+                dwell: secs(20),
+            },
+        ];
+        let mut carousel = Carousel::new(order, secs(10), secs(60));
+
+        let start = Instant::now();
+        carousel.restart(&pages, start);
+        assert_eq!(start + secs(20), carousel.deadline());
+
+        // Missing pages are skipped
+        assert!(carousel.rotate(&mut pages, start));
+        assert_eq!(Some("network"), pages.current_name());
+        assert_eq!(start + secs(5), carousel.deadline());
+        assert!(carousel.rotate(&mut pages, start));
+        assert_eq!(Some("overview"), pages.current_name());
+
+        // Pages outside of the carousel continue with its first one
+        pages.show("laptop");
+        assert!(carousel.rotate(&mut pages, start));
+        assert_eq!(Some("network"), pages.current_name());
+
+        // Pinned pages stay
+        pages.toggle_pin();
+        assert!(!carousel.rotate(&mut pages, start));
+        assert_eq!(Some("network"), pages.current_name());
+        assert_eq!(start + secs(5), carousel.deadline());
+
+        carousel.interrupt(start);
+        assert_eq!(start + secs(60), carousel.deadline());
+    }
+}
@@ -1,10 +1,16 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::carousel::Carousel;
+use crate::input::InputEvent;
+use crate::keymap::KeyMap;
+use crate::pages::Pages;
 use crate::params::{Layout, Parameters};
 use crate::ring_buffer::FixedRingBuffer;
 use crate::rule::Rule;
 use fb4rasp_shared::{NetworkInfo, SystemInfo};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::time::Instant;
 
 pub struct AnnotatedSystemInfo {
     pub source: String,
@@ -17,7 +23,11 @@ pub enum EngineCmdData {
     Net(NetworkInfo),
     SysInfo(AnnotatedSystemInfo),
     Touch(adafruit_mpr121::Mpr121TouchStatus),
+    Input(InputEvent),
     AddRule(Box<dyn Rule + Send>),
+    SetKeyMap(KeyMap),
+    SetPages(Vec<String>),
+    SetCarousel(Carousel),
     GetLastNetInfo(oneshot::Sender<(NetworkInfo, NetworkInfo)>),
     GetTouchInfo(oneshot::Sender<Vec<adafruit_mpr121::Mpr121TouchStatus>>),
     GetNetTxRx {
@@ -25,6 +35,8 @@ pub enum EngineCmdData {
         refresh_rate: std::time::Duration,
     },
     GetLayout(oneshot::Sender<Layout>),
+    GetTheme(oneshot::Sender<Option<String>>),
+    GetPages(oneshot::Sender<Pages>),
     GetSystemInfos(oneshot::Sender<HashMap<String, FixedRingBuffer<SystemInfo>>>),
 }
 
@@ -37,16 +49,18 @@ impl std::fmt::Debug for EngineCmdData {
 #[derive(Clone)]
 pub struct EngineHandle {
     sender: mpsc::Sender<EngineCmdData>,
+    redraw: Arc<Notify>,
 }
 
 impl EngineHandle {
     pub fn default() -> Self {
         let (tx, rx) = mpsc::channel(100);
+        let redraw = Arc::new(Notify::new());
 
-        let engine = Engine::new(rx);
+        let engine = Engine::new(rx, redraw.clone());
         tokio::spawn(run_engine(engine));
 
-        Self { sender: tx }
+        Self { sender: tx, redraw }
     }
 
     pub async fn send(&mut self, cmd: EngineCmdData) {
@@ -57,6 +71,16 @@ impl EngineHandle {
         let _ = self.sender.send(EngineCmdData::AddRule(rule)).await;
     }
 
+    /// Sets names of pages the UI cycles through.
+    pub async fn set_pages(&mut self, names: Vec<String>) {
+        let _ = self.sender.send(EngineCmdData::SetPages(names)).await;
+    }
+
+    /// Starts rotating pages, replacing any previous carousel.
+    pub async fn set_carousel(&mut self, carousel: Carousel) {
+        let _ = self.sender.send(EngineCmdData::SetCarousel(carousel)).await;
+    }
+
     pub async fn last_net_info(&self) -> (NetworkInfo, NetworkInfo) {
         let (sender, receiver) = oneshot::channel();
         let _ = self
@@ -72,6 +96,18 @@ impl EngineHandle {
         receiver.await.unwrap()
     }
 
+    /// Completes once the screen should be redrawn ahead of its regular
+    /// refresh, e.g. because of user input.
+    pub async fn redraw_requested(&self) {
+        self.redraw.notified().await
+    }
+
+    /// Redraws the screen ahead of its regular refresh, e.g. because the
+    /// terminal it is shown in was resized.
+    pub fn request_redraw(&self) {
+        self.redraw.notify_one();
+    }
+
     pub async fn get_system_infos(&self) -> HashMap<String, FixedRingBuffer<SystemInfo>> {
         let (sender, receiver) = oneshot::channel();
         let _ = self
@@ -98,24 +134,43 @@ impl EngineHandle {
         let _ = self.sender.send(EngineCmdData::GetLayout(sender)).await;
         receiver.await.unwrap()
     }
+
+    /// Theme chosen by an action, if any
+    pub async fn get_theme(&self) -> Option<String> {
+        let (sender, receiver) = oneshot::channel();
+        let _ = self.sender.send(EngineCmdData::GetTheme(sender)).await;
+        receiver.await.unwrap()
+    }
+
+    pub async fn pages(&self) -> Pages {
+        let (sender, receiver) = oneshot::channel();
+        let _ = self.sender.send(EngineCmdData::GetPages(sender)).await;
+        receiver.await.unwrap()
+    }
 }
 
 struct Engine {
     rules: Vec<Box<dyn Rule + Send>>,
+    key_map: KeyMap,
     params: Parameters,
     msg_rx: mpsc::Receiver<EngineCmdData>,
     sys_infos: HashMap<String, FixedRingBuffer<SystemInfo>>,
+    redraw: Arc<Notify>,
+    carousel: Option<Carousel>,
 }
 
 impl Engine {
     const DATA_SAMPLES: usize = (320 / 2) / 2;
 
-    fn new(msg_rx: mpsc::Receiver<EngineCmdData>) -> Self {
+    fn new(msg_rx: mpsc::Receiver<EngineCmdData>, redraw: Arc<Notify>) -> Self {
         let mut me = Engine {
             rules: Vec::new(),
+            key_map: KeyMap::default(),
             params: Parameters::default(),
             msg_rx,
             sys_infos: HashMap::new(),
+            redraw,
+            carousel: None,
         };
 
         me.sys_infos.insert(
@@ -141,9 +196,27 @@ impl Engine {
             }
             EngineCmdData::Touch(t) => {
                 self.params.touch_data.push(t);
+                self.interrupt_carousel();
                 self.event();
             }
+            EngineCmdData::Input(e) => {
+                self.interrupt_carousel();
+                if self.key_map.apply(&e, &mut self.params) {
+                    self.redraw.notify_one();
+                }
+            }
             EngineCmdData::AddRule(rule) => self.rules.push(rule),
+            EngineCmdData::SetKeyMap(key_map) => self.key_map = key_map,
+            EngineCmdData::SetPages(names) => {
+                self.params.pages.set(names);
+                if let Some(carousel) = &mut self.carousel {
+                    carousel.restart(&self.params.pages, Instant::now());
+                }
+            }
+            EngineCmdData::SetCarousel(mut carousel) => {
+                carousel.restart(&self.params.pages, Instant::now());
+                self.carousel = Some(carousel);
+            }
             EngineCmdData::GetLastNetInfo(sender) => {
                 let data = &self.params.net_infos;
                 let _ = sender.send((*data.item(-2), *data.last()));
@@ -184,12 +257,33 @@ impl Engine {
             EngineCmdData::GetLayout(sender) => {
                 let _ = sender.send(self.params.options.main_layout);
             }
+            EngineCmdData::GetTheme(sender) => {
+                let _ = sender.send(self.params.options.theme.clone());
+            }
+            EngineCmdData::GetPages(sender) => {
+                let _ = sender.send(self.params.pages.clone());
+            }
             EngineCmdData::GetSystemInfos(sender) => {
                 let _ = sender.send(self.sys_infos.clone());
             }
         }
     }
 
+    fn interrupt_carousel(&mut self) {
+        if let Some(carousel) = &mut self.carousel {
+            carousel.interrupt(Instant::now());
+        }
+    }
+
+    /// Shows the next page of the carousel once its deadline passed
+    fn rotate_carousel(&mut self) {
+        if let Some(carousel) = &mut self.carousel {
+            if carousel.rotate(&mut self.params.pages, Instant::now()) {
+                self.redraw.notify_one();
+            }
+        }
+    }
+
     fn event(&mut self) {
         let mut applied = false;
         for rule in &*self.rules {
@@ -197,12 +291,102 @@ impl Engine {
         }
         if applied {
             self.params.touch_data.clear();
+            self.redraw.notify_one();
         }
     }
 }
 
 async fn run_engine(mut engine: Engine) {
-    while let Some(msg) = engine.msg_rx.recv().await {
-        engine.handle_message(msg);
+    loop {
+        // Without a carousel there is nothing to wait for but messages
+        let deadline = engine.carousel.as_ref().map(|c| c.deadline());
+        let rotation = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now));
+        tokio::select! {
+            msg = engine.msg_rx.recv() => match msg {
+                Some(msg) => engine.handle_message(msg),
+                None => break,
+            },
+            _ = rotation, if deadline.is_some() => engine.rotate_carousel(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carousel::CarouselPage;
+    use crate::input::Gesture;
+    use crate::keymap::InputBinding;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn carousel_pauses_on_touch() {
+        let secs = Duration::from_secs;
+        let mut engine = EngineHandle::default();
+        engine
+            .set_pages(vec!["overview".into(), "laptop".into(), "network".into()])
+            .await;
+        let order = vec![
+            CarouselPage {
+                name: "overview".into(),
+                dwell: secs(10),
+            },
+            CarouselPage {
+                name: "network".into(),
+                dwell: secs(5),
+            },
+        ];
+        engine
+            .set_carousel(Carousel::new(order, secs(10), secs(30)))
+            .await;
+
+        tokio::time::sleep(secs(9)).await;
+        assert_eq!(Some("overview"), engine.pages().await.current_name());
+        tokio::time::sleep(secs(2)).await;
+        assert_eq!(Some("network"), engine.pages().await.current_name());
+
+        engine
+            .send(EngineCmdData::Input(InputEvent::Touch {
+                gesture: Gesture::Tap,
+                x: 0.0,
+                y: 0.0,
+            }))
+            .await;
+        tokio::time::sleep(secs(29)).await;
+        assert_eq!(Some("network"), engine.pages().await.current_name());
+        tokio::time::sleep(secs(2)).await;
+        assert_eq!(Some("overview"), engine.pages().await.current_name());
+    }
+
+    #[tokio::test]
+    async fn rotary_encoder_moves_between_pages() {
+        let mut engine = EngineHandle::default();
+        engine
+            .set_pages(vec!["overview".into(), "laptop".into(), "network".into()])
+            .await;
+        let mut key_map = KeyMap::default();
+        key_map.bind(
+            InputBinding::RotateClockwise,
+            crate::action::from_name("next").unwrap(),
+        );
+        key_map.bind(
+            InputBinding::RotateCounterClockwise,
+            crate::action::from_name("previous").unwrap(),
+        );
+        engine.send(EngineCmdData::SetKeyMap(key_map)).await;
+
+        assert_eq!(0, engine.pages().await.current());
+        engine
+            .send(EngineCmdData::Input(InputEvent::Rotate(1)))
+            .await;
+        assert_eq!(1, engine.pages().await.current());
+        engine
+            .send(EngineCmdData::Input(InputEvent::Rotate(2)))
+            .await;
+        assert_eq!(0, engine.pages().await.current());
+        engine
+            .send(EngineCmdData::Input(InputEvent::Rotate(-1)))
+            .await;
+        assert_eq!(Some("network"), engine.pages().await.current_name());
     }
 }
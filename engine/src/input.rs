@@ -0,0 +1,25 @@
+/// Touch gesture, as recognized by the display input layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gesture {
+    Touched,
+    Tap,
+    LongPress,
+    SwipeLeft,
+    SwipeRight,
+    SwipeUp,
+    SwipeDown,
+}
+
+/// User input, independent of the device it came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Touch {
+        gesture: Gesture,
+        x: f64,
+        y: f64,
+    },
+    /// Key press (or autorepeat) with its evdev code
+    Key(u16),
+    /// Rotary encoder steps, positive is clockwise
+    Rotate(i32),
+}
@@ -0,0 +1,110 @@
+use crate::action::Action;
+use crate::input::{Gesture, InputEvent};
+use crate::params::Parameters;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    /// Evdev key code
+    Key(u16),
+    RotateClockwise,
+    RotateCounterClockwise,
+    Tap,
+    LongPress,
+    SwipeLeft,
+    SwipeRight,
+    SwipeUp,
+    SwipeDown,
+}
+
+/// Maps keys, rotary encoder steps and touch gestures to actions.
+#[derive(Default)]
+pub struct KeyMap {
+    bindings: HashMap<InputBinding, Box<dyn Action + Send>>,
+}
+
+impl KeyMap {
+    pub fn bind(&mut self, binding: InputBinding, action: Box<dyn Action + Send>) {
+        self.bindings.insert(binding, action);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Applies the action bound to `event` (once per encoder step), returns
+    /// whether any action was applied.
+    pub fn apply(&self, event: &InputEvent, params: &mut Parameters) -> bool {
+        let (binding, count) = match event {
+            InputEvent::Key(code) => (InputBinding::Key(*code), 1),
+            InputEvent::Rotate(steps) if *steps > 0 => (InputBinding::RotateClockwise, *steps),
+            InputEvent::Rotate(steps) => (InputBinding::RotateCounterClockwise, -*steps),
+            InputEvent::Touch { gesture, .. } => {
+                let binding = match gesture {
+                    Gesture::Tap => InputBinding::Tap,
+                    Gesture::LongPress => InputBinding::LongPress,
+                    Gesture::SwipeLeft => InputBinding::SwipeLeft,
+                    Gesture::SwipeRight => InputBinding::SwipeRight,
+                    Gesture::SwipeUp => InputBinding::SwipeUp,
+                    Gesture::SwipeDown => InputBinding::SwipeDown,
+                    Gesture::Touched => return false,
+                };
+                (binding, 1)
+            }
+        };
+
+        let action = match self.bindings.get(&binding) {
+            Some(a) => a,
+            None => return false,
+        };
+
+        let mut applied = false;
+        for _ in 0..count {
+            applied |= action.apply(params);
+        }
+        applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::{NextPageAction, PinPageAction, PreviousPageAction};
+    use crate::pages::Pages;
+
+    #[test]
+    fn keys_and_rotation_are_mapped_to_actions() {
+        const KEY_UP: u16 = 103;
+        const KEY_ENTER: u16 = 28;
+
+        let mut key_map = KeyMap::default();
+        key_map.bind(InputBinding::Key(KEY_UP), Box::new(PreviousPageAction {}));
+        key_map.bind(InputBinding::Key(KEY_ENTER), Box::new(PinPageAction {}));
+        key_map.bind(InputBinding::RotateClockwise, Box::new(NextPageAction {}));
+
+        let mut params = Parameters {
+            pages: Pages::new(vec!["overview".into(), "hosts".into(), "network".into()]),
+            ..Parameters::default()
+        };
+        assert!(key_map.apply(&InputEvent::Key(KEY_UP), &mut params));
+        assert_eq!(Some("network"), params.pages.current_name());
+        assert!(key_map.apply(&InputEvent::Rotate(2), &mut params));
+        assert_eq!(Some("hosts"), params.pages.current_name());
+        assert!(!key_map.apply(&InputEvent::Rotate(-1), &mut params));
+        assert!(!key_map.apply(&InputEvent::Key(1), &mut params));
+        assert!(key_map.apply(&InputEvent::Key(KEY_ENTER), &mut params));
+        assert!(params.pages.pinned());
+
+        // Touch gestures go through the same bindings
+        key_map.bind(InputBinding::SwipeLeft, Box::new(NextPageAction {}));
+        let touch = |gesture| InputEvent::Touch {
+            gesture,
+            x: 0.0,
+            y: 0.0,
+        };
+        assert!(key_map.apply(&touch(Gesture::SwipeLeft), &mut params));
+        assert_eq!(Some("network"), params.pages.current_name());
+        assert!(!key_map.apply(&touch(Gesture::Touched), &mut params));
+        assert!(!key_map.apply(&touch(Gesture::LongPress), &mut params));
+    }
+}
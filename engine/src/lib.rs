@@ -1,6 +1,10 @@
 pub mod action;
+pub mod carousel;
 pub mod condition;
 pub mod engine;
+pub mod input;
+pub mod keymap;
+pub mod pages;
 pub mod params;
 pub mod ring_buffer;
 pub mod rule;
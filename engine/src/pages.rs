@@ -0,0 +1,143 @@
+/// Pages of the UI by name, in the order they are cycled through.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pages {
+    names: Vec<String>,
+    current: usize,
+    /// Whether the carousel stays on the current page
+    pinned: bool,
+}
+
+impl Pages {
+    pub fn new(names: Vec<String>) -> Self {
+        Self {
+            names,
+            current: 0,
+            pinned: false,
+        }
+    }
+
+    /// Replaces all pages, the current one is kept if it still exists.
+    pub fn set(&mut self, names: Vec<String>) {
+        let current = self.current_name().map(|n| n.to_owned());
+        self.names = names;
+        self.current = current
+            .and_then(|c| self.names.iter().position(|n| *n == c))
+            .unwrap_or(0);
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Index of the page shown
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_name(&self) -> Option<&str> {
+        self.names.get(self.current).map(|n| n.as_str())
+    }
+
+    /// Moves to the next page, the first one follows the last one.
+    pub fn show_next(&mut self) -> bool {
+        if self.names.len() < 2 {
+            return false;
+        }
+
+        self.current = (self.current + 1) % self.names.len();
+        true
+    }
+
+    /// Moves to the previous page, the last one precedes the first one.
+    pub fn show_previous(&mut self) -> bool {
+        if self.names.len() < 2 {
+            return false;
+        }
+
+        self.current = (self.current + self.names.len() - 1) % self.names.len();
+        true
+    }
+
+    /// Shows the first page and lets the carousel continue from it.
+    pub fn show_first(&mut self) -> bool {
+        if self.names.is_empty() {
+            return false;
+        }
+
+        let changed = self.current != 0 || self.pinned;
+        self.current = 0;
+        self.pinned = false;
+        changed
+    }
+
+    /// Whether the carousel stays on the current page
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Keeps the carousel on the current page, or lets it continue again.
+    pub fn toggle_pin(&mut self) -> bool {
+        if self.names.is_empty() {
+            return false;
+        }
+
+        self.pinned = !self.pinned;
+        true
+    }
+
+    /// Moves to the page with given name, returns false if there is none.
+    pub fn show(&mut self, name: &str) -> bool {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) => {
+                self.current = i;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_cycled() {
+        let mut pages = Pages::new(vec!["overview".into(), "laptop".into(), "network".into()]);
+        assert_eq!(Some("overview"), pages.current_name());
+        assert!(pages.show_previous());
+        assert_eq!(Some("network"), pages.current_name());
+        assert!(pages.show_next());
+        assert!(pages.show_next());
+        assert_eq!(1, pages.current());
+
+        assert!(!pages.show("alerts"));
+        assert!(pages.show("network"));
+        assert_eq!(2, pages.current());
+
+        // Current page is kept by name
+        pages.set(vec!["network".into(), "overview".into()]);
+        assert_eq!(0, pages.current());
+        pages.set(vec!["overview".into()]);
+        assert_eq!(Some("overview"), pages.current_name());
+        assert!(!pages.show_next());
+
+        assert!(pages.toggle_pin());
+        assert!(pages.pinned());
+        assert!(pages.show_first());
+        assert!(!pages.pinned());
+        assert!(!pages.show_first());
+
+        assert!(!Pages::default().show_next());
+        assert!(!Pages::default().toggle_pin());
+        assert_eq!(None, Pages::default().current_name());
+    }
+}
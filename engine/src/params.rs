@@ -1,13 +1,15 @@
+use crate::pages::Pages;
 use crate::ring_buffer::FixedRingBuffer;
 use fb4rasp_shared::NetworkInfo;
 
 pub struct Parameters {
     pub net_infos: FixedRingBuffer<NetworkInfo>,
     pub touch_data: Vec<adafruit_mpr121::Mpr121TouchStatus>,
+    pub pages: Pages,
     pub options: Options,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Layout {
     Horizontal,
     Vertical,
@@ -15,12 +17,15 @@ pub enum Layout {
 
 pub struct Options {
     pub main_layout: Layout,
+    /// Name of the color theme, the configured one if not set
+    pub theme: Option<String>,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             main_layout: Layout::Vertical,
+            theme: None,
         }
     }
 }
@@ -31,6 +36,7 @@ impl Default for Parameters {
         Self {
             net_infos: FixedRingBuffer::<NetworkInfo>::new(DATA_SAMPLES, NetworkInfo::default()),
             touch_data: Vec::default(),
+            pages: Pages::default(),
             options: Options::default(),
         }
     }
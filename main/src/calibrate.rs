@@ -0,0 +1,117 @@
+use display::{Color, Display, Fb4Rasp, Point, Rotation, TouchCalibration};
+use plotters::prelude::*;
+
+/// Calibration targets as fractions of the framebuffer size
+const TARGETS: [(f64, f64); 5] = [(0.1, 0.1), (0.9, 0.1), (0.9, 0.9), (0.1, 0.9), (0.5, 0.5)];
+const CROSS_SIZE: i32 = 10;
+const POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(20);
+/// A touch ends once no reading arrived for this long
+const RELEASE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+
+fn draw_target(fb: &mut Fb4Rasp, target: &Point, step: usize) {
+    fb.start();
+    fb.set_color(&Color {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.0,
+        alpha: 1.0,
+    });
+    fb.clean();
+    fb.set_font("DejaVuSansMono");
+    fb.set_font_size(16.0);
+    fb.set_color(&Color {
+        red: 0.9,
+        green: 0.9,
+        blue: 0.9,
+        alpha: 1.0,
+    });
+    let text = format!("Touch the cross ({}/{})", step + 1, TARGETS.len());
+    let size = fb.text_size(&text);
+    fb.render_text(
+        &Point {
+            x: (fb.width() as f64 - size.width) / 2.0,
+            y: fb.height() as f64 / 3.0,
+        },
+        &text,
+    );
+
+    {
+        let area = fb.get_backend().unwrap().into_drawing_area();
+        let (x, y) = (target.x as i32, target.y as i32);
+        let style = ShapeStyle::from(&RED).stroke_width(2);
+        let _ = area.draw(&PathElement::new(
+            vec![(x - CROSS_SIZE, y), (x + CROSS_SIZE, y)],
+            style,
+        ));
+        let _ = area.draw(&PathElement::new(
+            vec![(x, y - CROSS_SIZE), (x, y + CROSS_SIZE)],
+            style,
+        ));
+    }
+
+    fb.finish();
+}
+
+/// Averages raw readings of a single touch.
+async fn read_touch(fb: &mut Fb4Rasp) -> Point {
+    // Drop anything left from the previous target
+    let _ = fb.get_raw_touches();
+
+    let mut sum = Point { x: 0.0, y: 0.0 };
+    let mut count = 0;
+    let mut last_reading = std::time::Instant::now();
+    loop {
+        tokio::time::sleep(POLL_TIMEOUT).await;
+
+        let touches = fb.get_raw_touches();
+        if !touches.is_empty() {
+            for t in touches.iter() {
+                sum.x += t.x;
+                sum.y += t.y;
+            }
+            count += touches.len();
+            last_reading = std::time::Instant::now();
+        } else if count > 0 && last_reading.elapsed() >= RELEASE_TIMEOUT {
+            break;
+        }
+    }
+
+    Point {
+        x: sum.x / count as f64,
+        y: sum.y / count as f64,
+    }
+}
+
+/// Interactively collects touches on a set of targets and returns the
+/// resulting calibration.
+pub async fn calibrate(fb: &mut Fb4Rasp) -> Option<TouchCalibration> {
+    // Targets are in device coordinates, calibration is independent of rotation
+    fb.set_rotation(Rotation::Rotate0);
+    fb.init_events();
+
+    let (width, height) = (fb.width() as f64, fb.height() as f64);
+    let mut samples = Vec::with_capacity(TARGETS.len());
+    for (step, (tx, ty)) in TARGETS.iter().enumerate() {
+        let target = Point {
+            x: tx * width,
+            y: ty * height,
+        };
+        draw_target(fb, &target, step);
+
+        let raw = read_touch(fb).await;
+        log::debug!("Target {:?} touched at raw {:?}", &target, &raw);
+        samples.push((raw, Point { x: *tx, y: *ty }));
+    }
+
+    fb.start();
+    fb.set_color(&Color {
+        red: 0.0,
+        green: 0.0,
+        blue: 0.0,
+        alpha: 1.0,
+    });
+    fb.clean();
+    fb.finish();
+
+    TouchCalibration::from_samples(&samples)
+}
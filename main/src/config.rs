@@ -1,19 +1,289 @@
 use serde::Deserialize;
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
     #[serde(rename(serialize = "remote", deserialize = "remote"))]
     pub remotes: BTreeMap<String, Remote>,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    /// Key names (or `ROTARY_CW`/`ROTARY_CCW`, `TAP`, `LONG_PRESS` and
+    /// `SWIPE_LEFT`/`SWIPE_RIGHT`/`SWIPE_UP`/`SWIPE_DOWN`) mapped to action
+    /// names
+    #[serde(default = "default_keys")]
+    pub keys: BTreeMap<String, String>,
+    /// Pages rotate only if it is given
+    pub carousel: Option<CarouselConfig>,
+    #[serde(default, rename(deserialize = "themes"))]
+    theme_configs: BTreeMap<String, crate::theme::ThemeConfig>,
+    /// Built-in and configured themes, checked when the config is read
+    #[serde(skip)]
+    pub themes: BTreeMap<String, crate::theme::Theme>,
+    #[serde(default, rename(deserialize = "screen"))]
+    screen_configs: Vec<crate::screens::WidgetConfig>,
+    /// Screens of `[[screen]]` tables, checked when the config is read
+    #[serde(skip)]
+    pub screens: Vec<crate::screens::Screen>,
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
             remotes: BTreeMap::new(),
+            display: DisplayConfig::default(),
+            keys: default_keys(),
+            carousel: None,
+            theme_configs: BTreeMap::new(),
+            themes: crate::theme::builtin_themes(),
+            screen_configs: Vec::new(),
+            screens: Vec::new(),
         }
     }
+
+    /// Names of all hosts reporting their usage, the local one first
+    pub fn hosts(&self) -> Vec<String> {
+        std::iter::once(engine::engine::DEFAULT_HOST.to_owned())
+            .chain(self.remotes.keys().cloned())
+            .collect()
+    }
+
+    /// Colors of hosts in charts, remote colors are checked when the config
+    /// is read
+    pub fn host_colors(&self) -> crate::theme::HostColors {
+        let overrides = self
+            .remotes
+            .iter()
+            .filter_map(|(name, r)| {
+                let color = crate::screens::parse_color(r.color.as_ref()?)?;
+                Some((name.clone(), color))
+            })
+            .collect();
+        crate::theme::HostColors::new(self.hosts(), overrides)
+    }
+
+    pub fn key_map(&self) -> engine::keymap::KeyMap {
+        use engine::keymap::InputBinding;
+
+        let mut key_map = engine::keymap::KeyMap::default();
+        for (key, action_name) in self.keys.iter() {
+            let binding = match key.as_str() {
+                "ROTARY_CW" => Some(InputBinding::RotateClockwise),
+                "ROTARY_CCW" => Some(InputBinding::RotateCounterClockwise),
+                "TAP" => Some(InputBinding::Tap),
+                "LONG_PRESS" => Some(InputBinding::LongPress),
+                "SWIPE_LEFT" => Some(InputBinding::SwipeLeft),
+                "SWIPE_RIGHT" => Some(InputBinding::SwipeRight),
+                "SWIPE_UP" => Some(InputBinding::SwipeUp),
+                "SWIPE_DOWN" => Some(InputBinding::SwipeDown),
+                k => display::key_code(k).map(InputBinding::Key),
+            };
+            let action = engine::action::from_name(action_name);
+            match (binding, action) {
+                (Some(b), Some(a)) => key_map.bind(b, a),
+                (None, _) => log::error!("Unknown key {} in config", key),
+                (_, None) => log::error!("Unknown action {} for key {}", action_name, key),
+            }
+        }
+
+        key_map
+    }
+}
+
+fn default_keys() -> BTreeMap<String, String> {
+    [
+        ("KEY_UP", "previous"),
+        ("KEY_LEFT", "previous"),
+        ("KEY_DOWN", "next"),
+        ("KEY_RIGHT", "next"),
+        ("KEY_ENTER", "select"),
+        ("KEY_KPENTER", "select"),
+        ("KEY_SELECT", "select"),
+        ("KEY_OK", "select"),
+        ("KEY_ESC", "back"),
+        ("KEY_BACK", "back"),
+        ("ROTARY_CW", "next"),
+        ("ROTARY_CCW", "previous"),
+        ("KEY_PAGEDOWN", "next"),
+        ("KEY_PAGEUP", "previous"),
+        ("SWIPE_LEFT", "next"),
+        ("SWIPE_RIGHT", "previous"),
+        ("LONG_PRESS", "select"),
+        ("SWIPE_DOWN", "back"),
+    ]
+    .iter()
+    .map(|(k, a)| (k.to_string(), a.to_string()))
+    .collect()
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct DisplayConfig {
+    pub framebuffer: Option<PathBuf>,
+    /// Clockwise rotation in degrees, one of 0, 90, 180 or 270
+    #[serde(default)]
+    pub rotation: u32,
+    /// Touchscreen calibration matrix, as written by `fb4rasp calibrate`
+    pub calibration: Option<[f64; 6]>,
+    /// PNG or SVG images by name, relative paths are relative to the config
+    /// file
+    #[serde(default)]
+    pub images: BTreeMap<String, PathBuf>,
+    /// TrueType or OpenType files of font roles (regular, bold, mono, icon),
+    /// glyphs missing in a file are taken from the following ones. Relative
+    /// paths are relative to the config file.
+    #[serde(default)]
+    pub fonts: BTreeMap<String, Vec<PathBuf>>,
+    /// Name of the color theme shown at start
+    pub theme: Option<String>,
+    /// Files written by the svg backend
+    #[serde(default)]
+    pub output: OutputConfig,
+    /// VNC server showing the screen, disabled if not given
+    pub vnc: Option<VncConfig>,
+    /// HTTP server with screenshots and a live view, disabled if not given
+    pub http: Option<HttpConfig>,
+}
+
+impl DisplayConfig {
+    pub fn rotation(&self) -> display::Rotation {
+        display::Rotation::from_degrees(self.rotation).unwrap_or_else(|| {
+            log::error!("Unsupported display rotation {}, using 0", self.rotation);
+            display::Rotation::Rotate0
+        })
+    }
+
+    pub fn touch_calibration(&self) -> Option<display::TouchCalibration> {
+        self.calibration.map(display::TouchCalibration::new)
+    }
+
+    pub fn theme(&self) -> String {
+        self.theme
+            .clone()
+            .unwrap_or_else(|| crate::theme::DEFAULT_THEME.to_owned())
+    }
+}
+
+/// Output of the svg backend, relative paths are relative to the working
+/// directory.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// svg, pdf or png, taken from the extension of `path` if not given
+    pub format: Option<String>,
+    /// File rewritten by every frame, or the directory of numbered frames
+    pub path: Option<PathBuf>,
+    /// Writes numbered frames, only this many newest ones are kept
+    pub frames: Option<usize>,
+}
+
+impl OutputConfig {
+    pub fn output(&self) -> Result<display::VectorOutput, String> {
+        use display::VectorFormat;
+
+        let format = match self.format.as_deref() {
+            Some(format) => Some(format),
+            None if self.frames.is_none() => self
+                .path
+                .as_ref()
+                .and_then(|p| p.extension())
+                .and_then(|e| e.to_str()),
+            None => None,
+        };
+        let format = match format {
+            None | Some("svg") => VectorFormat::Svg,
+            Some("pdf") => VectorFormat::Pdf,
+            Some("png") => VectorFormat::Png,
+            Some(f) => return Err(format!("Unknown output format {}", f)),
+        };
+        if self.frames == Some(0) {
+            return Err("At least one output frame has to be kept".to_owned());
+        }
+
+        let path = self.path.clone().unwrap_or_else(|| match self.frames {
+            Some(_) => PathBuf::from("output/frames"),
+            None => display::VectorOutput::default()
+                .path
+                .with_extension(format.extension()),
+        });
+        Ok(display::VectorOutput {
+            format,
+            path,
+            frames: self.frames,
+        })
+    }
+}
+
+/// VNC (RFB) server without authentication, so it listens on loopback by
+/// default, to be reached e.g. through an SSH tunnel.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VncConfig {
+    #[serde(default = "default_vnc_listen")]
+    pub listen: std::net::SocketAddr,
+}
+
+fn default_vnc_listen() -> std::net::SocketAddr {
+    ([127, 0, 0, 1], 5900).into()
+}
+
+/// HTTP server serving `/screenshot.png` and `/live`, without
+/// authentication like the VNC server.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    #[serde(default = "default_http_listen")]
+    pub listen: std::net::SocketAddr,
+}
+
+fn default_http_listen() -> std::net::SocketAddr {
+    ([127, 0, 0, 1], 8080).into()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CarouselConfig {
+    /// Seconds a page is shown, unless set for the page
+    #[serde(default = "default_dwell")]
+    pub dwell: u64,
+    /// Seconds without rotation after a touch or any other input
+    #[serde(default = "default_pause")]
+    pub pause: u64,
+    /// Pages in the order they are shown, all of them if not given
+    #[serde(default, rename(deserialize = "page"))]
+    pub pages: Vec<CarouselPageConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CarouselPageConfig {
+    pub name: String,
+    pub dwell: Option<u64>,
+}
+
+const fn default_dwell() -> u64 {
+    15
+}
+
+const fn default_pause() -> u64 {
+    60
+}
+
+impl CarouselConfig {
+    pub fn carousel(&self) -> engine::carousel::Carousel {
+        use std::time::Duration;
+
+        let order = self
+            .pages
+            .iter()
+            .map(|p| engine::carousel::CarouselPage {
+                name: p.name.clone(),
+                dwell: Duration::from_secs(p.dwell.unwrap_or(self.dwell)),
+            })
+            .collect();
+        engine::carousel::Carousel::new(
+            order,
+            Duration::from_secs(self.dwell),
+            Duration::from_secs(self.pause),
+        )
+    }
 }
 
 const fn truer() -> bool {
@@ -25,6 +295,8 @@ pub struct Remote {
     pub ip: String,
     #[serde(default = "truer")]
     pub enable: bool,
+    /// Color of the remote in charts, `#rrggbb[aa]`
+    pub color: Option<String>,
 }
 
 pub fn read_toml_config<P: AsRef<Path>>(path: P) -> Option<Config> {
@@ -39,7 +311,61 @@ pub fn read_toml_config<P: AsRef<Path>>(path: P) -> Option<Config> {
 
         let t: Result<Config, _> = toml::from_str(config.as_str());
         match t {
-            Ok(content) => Some(content),
+            Ok(mut content) => {
+                if let Some(dir) = path.parent() {
+                    for image in content.display.images.values_mut() {
+                        *image = dir.join(&image);
+                    }
+                    for font in content.display.fonts.values_mut().flatten() {
+                        *font = dir.join(&font);
+                    }
+                }
+
+                match crate::theme::parse_themes(&content.theme_configs) {
+                    Ok(themes) => content.themes = themes,
+                    Err(e) => {
+                        log::error!("Invalid theme in config {:?} file: {}", &path, &e);
+                        return None;
+                    }
+                }
+                if !content.themes.contains_key(&content.display.theme()) {
+                    log::error!(
+                        "Unknown theme {} in config {:?} file",
+                        content.display.theme(),
+                        &path
+                    );
+                    return None;
+                }
+
+                if let Err(e) = content.display.output.output() {
+                    log::error!("Invalid output in config {:?} file: {}", &path, &e);
+                    return None;
+                }
+
+                for (name, remote) in content.remotes.iter() {
+                    if let Some(color) = &remote.color {
+                        if crate::screens::parse_color(color).is_none() {
+                            log::error!(
+                                "Invalid color '{}' of remote {} in config {:?} file",
+                                color,
+                                name,
+                                &path
+                            );
+                            return None;
+                        }
+                    }
+                }
+
+                let hosts = content.hosts();
+                match crate::screens::parse_screens(&content.screen_configs, &config, &hosts) {
+                    Ok(screens) => content.screens = screens,
+                    Err(e) => {
+                        log::error!("Invalid screen in config {:?} file: {}", &path, &e);
+                        return None;
+                    }
+                }
+                Some(content)
+            }
             Err(e) => {
                 log::error!("Failed to parse config {:?} file: {}", &path, &e);
                 None
@@ -49,3 +375,118 @@ pub fn read_toml_config<P: AsRef<Path>>(path: P) -> Option<Config> {
 
     inner(path.as_ref())
 }
+
+/// Stores the touchscreen calibration in the `[display]` table of the config
+/// file, the rest of the file including comments is kept as it is.
+pub fn write_calibration<P: AsRef<Path>>(
+    path: P,
+    calibration: &display::TouchCalibration,
+) -> Result<(), String> {
+    fn inner(path: &Path, calibration: &display::TouchCalibration) -> Result<(), String> {
+        let config = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read config {:?} file: {}", &path, &e)),
+        };
+
+        let content = set_calibration(&config, &calibration.matrix)
+            .map_err(|e| format!("Failed to update config {:?}: {}", &path, e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write config {:?} file: {}", &path, &e))
+    }
+
+    inner(path.as_ref(), calibration)
+}
+
+/// Sets `calibration` in the `[display]` table of `config`, which is added if
+/// missing.
+fn set_calibration(config: &str, matrix: &[f64; 6]) -> Result<String, String> {
+    let mut config = config
+        .parse::<toml_edit::Document>()
+        .map_err(|e| e.to_string())?;
+    if !config.contains_key("display") {
+        config["display"] = toml_edit::table();
+    }
+    let display = config["display"]
+        .as_table_like_mut()
+        .ok_or_else(|| "entry 'display' is not a table".to_owned())?;
+
+    let mut value = toml_edit::Value::from(matrix.iter().copied().collect::<toml_edit::Array>());
+    match display.get_mut("calibration") {
+        // Comments around the old value are kept
+        Some(item) => {
+            if let Some(old) = item.as_value() {
+                *value.decor_mut() = old.decor().clone();
+            }
+            *item = toml_edit::Item::Value(value);
+        }
+        None => {
+            display.insert("calibration", toml_edit::Item::Value(value));
+        }
+    }
+    Ok(config.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration_is_replaced_in_place() {
+        let matrix = [2.0, 0.0, 0.5, 0.0, 2.0, -0.5];
+        let config = r#"# Comment
+[remote."laptop.lan"]
+host = "[laptop]"
+calibration = 1
+motd = """
+[display]
+calibration = 0
+"""
+
+[display] # panel
+# Touch
+calibration = [
+  1.0, 0.0, 0.0,
+  0.0, 1.0, 0.0,
+] # measured
+rotation = 90 # degrees
+
+[display.vnc]
+"#;
+        assert_eq!(
+            r#"# Comment
+[remote."laptop.lan"]
+host = "[laptop]"
+calibration = 1
+motd = """
+[display]
+calibration = 0
+"""
+
+[display] # panel
+# Touch
+calibration = [2.0, 0.0, 0.5, 0.0, 2.0, -0.5] # measured
+rotation = 90 # degrees
+
+[display.vnc]
+"#,
+            set_calibration(config, &matrix).unwrap()
+        );
+
+        // Commented out keys stay
+        let config = "[display]\n# calibration = [1.0]\nrotation = 90\n";
+        let updated = set_calibration(config, &matrix).unwrap();
+        assert!(updated.starts_with("[display]\n# calibration = [1.0]\nrotation = 90\n"));
+        assert!(updated.contains("\ncalibration = [2.0, 0.0, 0.5, 0.0, 2.0, -0.5]\n"));
+
+        assert_eq!(
+            "[remote.laptop]\n\n[display]\ncalibration = [2.0, 0.0, 0.5, 0.0, 2.0, -0.5]\n",
+            set_calibration("[remote.laptop]\n", &matrix).unwrap()
+        );
+        assert_eq!(
+            "[display]\ncalibration = [2.0, 0.0, 0.5, 0.0, 2.0, -0.5]\n",
+            set_calibration("", &matrix).unwrap()
+        );
+        assert!(set_calibration("display = 1\n", &matrix).is_err());
+    }
+}
@@ -24,6 +24,7 @@ impl IntoIterator for SummaryMemUsage {
 pub struct SeriesData<T> {
     pub data: T,
     pub name: String,
+    pub color: RGBAColor,
 }
 
 pub trait Countable {
@@ -62,6 +63,13 @@ where
     }
 }
 
+/// Colors of a chart apart from its series
+pub struct ChartColors {
+    pub labels: RGBAColor,
+    pub axis: RGBAColor,
+    pub legend: RGBAColor,
+}
+
 pub struct PlotData<T>
 where
     T: IntoIterator,
@@ -73,8 +81,7 @@ where
 
 pub fn plot_data<T, V, DB>(
     plot: &DrawingArea<DB, Shift>,
-    text_color: &RGBColor,
-    color_index: &mut usize,
+    colors: &ChartColors,
     left_axis: PlotData<T>,
     right_axis: PlotData<V>,
 ) where
@@ -121,38 +128,31 @@ pub fn plot_data<T, V, DB>(
     let should_draw_legend = series_count > 1;
     left_axis.data.into_iter().for_each(|series| {
         let name = series.name.to_owned();
-        let ci = *color_index;
-        *color_index += 1;
-
-        let ls = LineSeries::new(
-            series.into_iter().enumerate().map(|(i, v)| (i, v)),
-            &Palette99::pick(ci),
-        );
+        let color = series.color;
+        let ls = LineSeries::new(series.into_iter().enumerate().map(|(i, v)| (i, v)), &color);
         let line_series = chart.draw_series(ls).unwrap();
 
         if should_draw_legend {
             line_series.label(name).legend(move |(x, y)| {
-                PathElement::new(vec![(x - 50, y - 5), (x - 30, y - 5)], &Palette99::pick(ci))
+                PathElement::new(vec![(x - 50, y - 5), (x - 30, y - 5)], color)
             });
         }
     });
 
+    // Right axis series are fainter to tell them apart from left axis ones
     right_axis.data.into_iter().for_each(|series| {
+        let color = series.color.mix(0.5);
         chart
             .draw_secondary_series(LineSeries::new(
                 series.into_iter().enumerate().map(|(i, v)| (i, v)),
-                &Palette99::pick(*color_index),
+                &color,
             ))
             .unwrap();
-        *color_index += 1;
     });
 
     let labels_font = TextStyle {
         font: FontDesc::new(FontFamily::Monospace, 12.0, FontStyle::Normal),
-        color: plotters_backend::BackendColor {
-            alpha: 1.0,
-            rgb: text_color.rgb(),
-        },
+        color: colors.labels.to_backend_color(),
         pos: text_anchor::Pos::new(text_anchor::HPos::Left, text_anchor::VPos::Center),
     };
 
@@ -163,7 +163,7 @@ pub fn plot_data<T, V, DB>(
         .y_labels(5)
         .set_tick_mark_size(LabelAreaPosition::Left, -5)
         .y_label_formatter(&left_axis.formatter)
-        .axis_style(&RED)
+        .axis_style(colors.axis)
         .label_style(labels_font.clone())
         .draw()
         .unwrap();
@@ -173,7 +173,7 @@ pub fn plot_data<T, V, DB>(
         .y_labels(5)
         .set_tick_mark_size(LabelAreaPosition::Right, -5)
         .y_label_formatter(&right_axis.formatter)
-        .axis_style(&RED)
+        .axis_style(colors.axis)
         .label_style(labels_font)
         .draw()
         .unwrap();
@@ -181,10 +181,7 @@ pub fn plot_data<T, V, DB>(
     if should_draw_legend {
         let legent_font = TextStyle {
             font: FontDesc::new(FontFamily::Monospace, 10.0, FontStyle::Normal),
-            color: plotters_backend::BackendColor {
-                alpha: 0.6,
-                rgb: (200, 200, 200),
-            },
+            color: colors.legend.to_backend_color(),
             pos: text_anchor::Pos::new(text_anchor::HPos::Right, text_anchor::VPos::Center),
         };
         chart
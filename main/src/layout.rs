@@ -0,0 +1,273 @@
+use crate::widgets::Snapshot;
+use display::Rect;
+
+/// Part of a screen, renders itself into the area given by its container.
+pub trait Widget<DB> {
+    /// Height needed for given width, used by `Size::Auto` in columns
+    fn preferred_height(&self, _fb: &mut DB, _width: f64, _data: &Snapshot) -> f64 {
+        0.0
+    }
+
+    /// Width needed for given height, used by `Size::Auto` in rows
+    fn preferred_width(&self, _fb: &mut DB, _height: f64, _data: &Snapshot) -> f64 {
+        0.0
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot);
+}
+
+/// Share of a row or column taken by a widget.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// As much as the widget prefers
+    Auto,
+    /// Given number of pixels
+    Fixed(f64),
+    /// Part of the space left by other widgets, proportional to the weight
+    Weight(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// Offsets and lengths of consecutive parts of `total` pixels.
+fn distribute(total: f64, sizes: &[Size], natural: &[f64], spacing: f64) -> Vec<(f64, f64)> {
+    let gaps = spacing * sizes.len().saturating_sub(1) as f64;
+    let mut taken = 0.0;
+    let mut weights = 0.0;
+    for (size, natural) in sizes.iter().zip(natural.iter()) {
+        match size {
+            Size::Auto => taken += natural,
+            Size::Fixed(length) => taken += length,
+            Size::Weight(weight) => weights += weight,
+        }
+    }
+
+    let free = (total - gaps - taken).max(0.0);
+    let mut offset = 0.0;
+    sizes
+        .iter()
+        .zip(natural.iter())
+        .map(|(size, natural)| {
+            let length = match size {
+                Size::Auto => *natural,
+                Size::Fixed(length) => *length,
+                Size::Weight(weight) if weights > 0.0 => free * weight / weights,
+                Size::Weight(_) => 0.0,
+            };
+            let span = (offset, length);
+            offset += length + spacing;
+            span
+        })
+        .collect()
+}
+
+fn inset(area: &Rect, padding: f64) -> Rect {
+    Rect {
+        x: area.x + padding,
+        y: area.y + padding,
+        width: (area.width - 2.0 * padding).max(0.0),
+        height: (area.height - 2.0 * padding).max(0.0),
+    }
+}
+
+/// Row or column of widgets.
+pub struct Stack<DB> {
+    direction: Direction,
+    /// Space around all children
+    padding: f64,
+    /// Space between consecutive children
+    spacing: f64,
+    children: Vec<(Size, Box<dyn Widget<DB>>)>,
+}
+
+impl<DB> Stack<DB> {
+    pub fn new(direction: Direction, padding: f64, spacing: f64) -> Self {
+        Self {
+            direction,
+            padding,
+            spacing,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn column(padding: f64, spacing: f64) -> Self {
+        Self::new(Direction::Vertical, padding, spacing)
+    }
+
+    pub fn add(&mut self, size: Size, widget: Box<dyn Widget<DB>>) {
+        self.children.push((size, widget));
+    }
+
+    /// Length of a child along the direction of the stack, if it is known
+    /// without any layout.
+    fn natural(
+        &self,
+        fb: &mut DB,
+        size: Size,
+        widget: &dyn Widget<DB>,
+        cross: f64,
+        data: &Snapshot,
+    ) -> f64 {
+        match (size, self.direction) {
+            (Size::Auto, Direction::Horizontal) => widget.preferred_width(fb, cross, data),
+            (Size::Auto, Direction::Vertical) => widget.preferred_height(fb, cross, data),
+            (Size::Fixed(length), _) => length,
+            (Size::Weight(_), _) => 0.0,
+        }
+    }
+
+    /// Total preferred length along `direction` given the length across it
+    fn preferred(&self, fb: &mut DB, direction: Direction, cross: f64, data: &Snapshot) -> f64 {
+        let cross = (cross - 2.0 * self.padding).max(0.0);
+        let lengths = self.children.iter().map(|(size, widget)| {
+            if direction == self.direction {
+                self.natural(fb, *size, widget.as_ref(), cross, data)
+            } else {
+                match direction {
+                    Direction::Horizontal => widget.preferred_width(fb, cross, data),
+                    Direction::Vertical => widget.preferred_height(fb, cross, data),
+                }
+            }
+        });
+
+        let content = if direction == self.direction {
+            let gaps = self.spacing * self.children.len().saturating_sub(1) as f64;
+            lengths.sum::<f64>() + gaps
+        } else {
+            lengths.fold(0.0, f64::max)
+        };
+        content + 2.0 * self.padding
+    }
+}
+
+impl<DB> Widget<DB> for Stack<DB> {
+    fn preferred_height(&self, fb: &mut DB, width: f64, data: &Snapshot) -> f64 {
+        self.preferred(fb, Direction::Vertical, width, data)
+    }
+
+    fn preferred_width(&self, fb: &mut DB, height: f64, data: &Snapshot) -> f64 {
+        self.preferred(fb, Direction::Horizontal, height, data)
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let inner = inset(area, self.padding);
+        let (total, cross) = match self.direction {
+            Direction::Horizontal => (inner.width, inner.height),
+            Direction::Vertical => (inner.height, inner.width),
+        };
+
+        let sizes: Vec<Size> = self.children.iter().map(|(size, _)| *size).collect();
+        let natural: Vec<f64> = self
+            .children
+            .iter()
+            .map(|(size, widget)| self.natural(fb, *size, widget.as_ref(), cross, data))
+            .collect();
+        let spans = distribute(total, &sizes, &natural, self.spacing);
+        for ((_, widget), (offset, length)) in self.children.iter().zip(spans) {
+            let rect = match self.direction {
+                Direction::Horizontal => Rect {
+                    x: inner.x + offset,
+                    y: inner.y,
+                    width: length,
+                    height: inner.height,
+                },
+                Direction::Vertical => Rect {
+                    x: inner.x,
+                    y: inner.y + offset,
+                    width: inner.width,
+                    height: length,
+                },
+            };
+            widget.render(fb, &rect, data);
+        }
+    }
+}
+
+/// Widgets in cells of a grid, filled row by row.
+pub struct Grid<DB> {
+    /// Weights of columns and rows
+    columns: Vec<f64>,
+    rows: Vec<f64>,
+    padding: f64,
+    spacing: f64,
+    cells: Vec<Box<dyn Widget<DB>>>,
+}
+
+impl<DB> Grid<DB> {
+    pub fn new(columns: Vec<f64>, rows: Vec<f64>, padding: f64, spacing: f64) -> Self {
+        Self {
+            columns,
+            rows,
+            padding,
+            spacing,
+            cells: Vec::new(),
+        }
+    }
+
+    /// Returns false if the grid is already full
+    pub fn add(&mut self, widget: Box<dyn Widget<DB>>) -> bool {
+        if self.cells.len() >= self.columns.len() * self.rows.len() {
+            return false;
+        }
+
+        self.cells.push(widget);
+        true
+    }
+}
+
+impl<DB> Widget<DB> for Grid<DB> {
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        fn spans(total: f64, weights: &[f64], spacing: f64) -> Vec<(f64, f64)> {
+            let sizes: Vec<Size> = weights.iter().map(|w| Size::Weight(*w)).collect();
+            distribute(total, &sizes, &vec![0.0; sizes.len()], spacing)
+        }
+
+        if self.columns.is_empty() {
+            return;
+        }
+
+        let inner = inset(area, self.padding);
+        let columns = spans(inner.width, &self.columns, self.spacing);
+        let rows = spans(inner.height, &self.rows, self.spacing);
+        for (i, widget) in self.cells.iter().enumerate() {
+            let (x, width) = columns[i % columns.len()];
+            let (y, height) = rows[i / columns.len()];
+            let rect = Rect {
+                x: inner.x + x,
+                y: inner.y + y,
+                width,
+                height,
+            };
+            widget.render(fb, &rect, data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_is_distributed() {
+        use Size::*;
+
+        assert_eq!(
+            vec![(0.0, 20.0), (24.0, 10.0), (38.0, 40.0), (82.0, 20.0)],
+            distribute(
+                102.0,
+                &[Auto, Fixed(10.0), Weight(2.0), Weight(1.0)],
+                &[20.0, 0.0, 0.0, 0.0],
+                4.0
+            )
+        );
+        // Weighted widgets get nothing if there is no space left
+        assert_eq!(
+            vec![(0.0, 30.0), (30.0, 0.0)],
+            distribute(20.0, &[Fixed(30.0), Weight(1.0)], &[0.0, 0.0], 0.0)
+        );
+    }
+}
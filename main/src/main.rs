@@ -1,21 +1,32 @@
-use display::{CairoSvg, Color, Display, Fb4Rasp, Point};
+use display::{
+    CairoImage, CairoSvg, CairoTerminal, Display, EventType, Fb4Rasp, InputReader, Rect, VncInput,
+    VncServer,
+};
 use engine::{
     action, condition,
     engine::{AnnotatedSystemInfo, EngineCmdData},
-    params::{Layout, Parameters},
+    input::{Gesture, InputEvent},
+    params::Layout,
     rule, EngineHandle,
 };
 use fb4rasp_shared::{CpuUsage, MemInfo, NetworkInfo, SystemInfo};
 use rand::distributions::Distribution;
 use session::{SshSession, WsSession};
-use std::{cmp::max, path::PathBuf};
+use std::{collections::BTreeMap, path::PathBuf};
 use structopt::StructOpt;
 use sysinfo::{ProcessorExt, SystemExt};
 
+mod calibrate;
 mod config;
 
 mod helpers;
-use crate::helpers::{PlotData, SeriesData, SummaryMemUsage};
+mod layout;
+mod screens;
+mod theme;
+mod web;
+mod widgets;
+
+use crate::layout::Widget;
 
 // A basic example
 #[derive(StructOpt, Debug)]
@@ -29,38 +40,124 @@ struct CmdLineOptions {
     /// Output file
     #[structopt(short, long, parse(from_os_str))]
     config: Option<PathBuf>,
+
+    /// Display backend: auto, fb, svg (also PDF or PNG, see `[display.output]`
+    /// of the config), png or terminal. Auto uses the framebuffer if there is
+    /// one, otherwise png, which writes every frame to output/screen.png
+    #[structopt(short, long, default_value = "auto")]
+    display: DisplayKind,
+
+    /// Framebuffer device used by the fb backend (overrides config file)
+    #[structopt(short, long, parse(from_os_str))]
+    framebuffer: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Lists available framebuffer devices and exits
+    ListDisplays,
+    /// Calibrates the touchscreen and stores the result in the config file
+    Calibrate,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DisplayKind {
+    Auto,
+    Framebuffer,
+    Svg,
+    Png,
+    /// Frames drawn in the terminal, e.g. for debugging over SSH
+    Terminal,
+}
+
+impl std::str::FromStr for DisplayKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(DisplayKind::Auto),
+            "fb" => Ok(DisplayKind::Framebuffer),
+            "svg" => Ok(DisplayKind::Svg),
+            "png" => Ok(DisplayKind::Png),
+            "terminal" => Ok(DisplayKind::Terminal),
+            _ => Err(format!("Unknown display backend: {}", s)),
+        }
+    }
 }
 
 const DRAW_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
 const NET_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
 const TOUCH_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
 const REMOTE_REFRESH_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
+const PNG_SNAPSHOT_PATH: &str = "output/screen.png";
+/// Size of small TFT panels, text stays legible when scaled down to the
+/// terminal
+const TERMINAL_SIZE: (usize, usize) = (480, 320);
+const DEFAULT_FRAMEBUFFER: &str = "/dev/fb0";
+
+/// What is shown on the screen, taken from the config
+struct ScreenContent {
+    screens: Vec<screens::Screen>,
+    hosts: Vec<String>,
+    themes: BTreeMap<String, theme::Theme>,
+    host_colors: theme::HostColors,
+}
 
-async fn render_screen(engine_handle: EngineHandle) {
-    async fn render_screen_internal<DB>(mut engine_handle: EngineHandle, mut fb: DB)
+async fn render_screen(
+    engine_handle: EngineHandle,
+    display: DisplayKind,
+    config: config::DisplayConfig,
+    content: ScreenContent,
+) {
+    /// Configured screens replace built-in pages, which follow the layout
+    fn build_pages<DB>(
+        screens: &[screens::Screen],
+        hosts: &[String],
+        layout: Layout,
+    ) -> Vec<(String, Box<dyn Widget<DB>>)>
     where
-        for<'a> DB: Display<'a>,
+        for<'a> DB: Display<'a> + 'static,
     {
-        fn print_touch_status(ts: &adafruit_mpr121::Mpr121TouchStatus) -> String {
-            let mut status = String::new();
-            let mut separator = "";
-            for i in adafruit_mpr121::Mpr121TouchStatus::first()
-                ..=adafruit_mpr121::Mpr121TouchStatus::last()
-            {
-                if ts.touched(i) {
-                    status += separator;
-                    status += &format!("{}", i);
-                    separator = ", ";
-                }
-            }
-
-            status
+        if screens.is_empty() {
+            return widgets::default_pages(layout, hosts);
         }
 
-        let mut x: i32;
-        let mut y: i32;
+        screens
+            .iter()
+            .map(|s| (s.name.clone(), s.root.build()))
+            .collect()
+    }
 
-        fb.init_events();
+    async fn render_screen_internal<DB>(
+        mut engine_handle: EngineHandle,
+        mut fb: DB,
+        images: BTreeMap<String, PathBuf>,
+        fonts: BTreeMap<String, Vec<PathBuf>>,
+        default_theme: String,
+        content: ScreenContent,
+        sinks: FrameSinks,
+    ) where
+        for<'a> DB: Display<'a> + 'static,
+    {
+        let ScreenContent {
+            screens,
+            hosts,
+            themes,
+            host_colors,
+        } = content;
+        for (name, path) in images.iter() {
+            if let Err(e) = fb.load_image(name, path) {
+                log::error!("Failed to load image {} from {:?}: {:?}", name, path, e);
+            }
+        }
+        for (role, paths) in fonts.iter() {
+            if let Err(e) = fb.load_font(role, paths) {
+                log::error!("Failed to load font {} from {:?}: {:?}", role, paths, e);
+            }
+        }
 
         let dist_uni = rand::distributions::Uniform::from(0..5);
         let mut rng = rand::thread_rng();
@@ -72,6 +169,15 @@ async fn render_screen(engine_handle: EngineHandle) {
         let mut screensaver: usize = 0;
         let mut shift = 0;
 
+        let mut theme_name = default_theme.clone();
+        let mut theme = themes[&theme_name].clone();
+
+        let mut layout = engine_handle.get_main_layout().await;
+        let mut pages = build_pages::<DB>(&screens, &hosts, layout);
+        engine_handle
+            .set_pages(pages.iter().map(|(name, _)| name.clone()).collect())
+            .await;
+
         let mut interval = tokio::time::interval(DRAW_REFRESH_TIMEOUT);
         loop {
             system.refresh_cpu();
@@ -84,53 +190,12 @@ async fn render_screen(engine_handle: EngineHandle) {
                 screensaver += 1;
             }
 
-            x = shift;
-            y = 16;
-            fb.start();
-            fb.set_font("DejaVuSansMono");
-            fb.set_color(&Color {
-                red: 0.0,
-                green: 0.0,
-                blue: 0.0,
-                alpha: 1.0,
-            });
-            fb.clean();
-            let local_time = chrono::Local::now();
-            fb.set_color(&Color {
-                red: 0.9,
-                green: 0.9,
-                blue: 0.9,
-                alpha: 1.0,
-            });
-            fb.set_font_size(22.0);
-            fb.render_text(
-                &Point {
-                    x: x as f64,
-                    y: y as f64,
-                },
-                local_time
-                    .format("%a, %d.%m.%Y, %H:%M:%S")
-                    .to_string()
-                    .as_str(),
-            );
-            y += 20;
-
             let mut cpu_usage = CpuUsage::default();
-            let mut cpu_info_str = String::new();
             {
                 let processors = system.get_processors();
                 let count = processors.len();
-                cpu_usage.detailed.resize(count, 0.0);
-                let mut avg: f32 = 0.0;
-                let mut separator = "";
-                for (i, p) in processors.iter().enumerate() {
-                    let p_usage = p.get_cpu_usage();
-                    cpu_info_str.push_str(&format!("{}{:>2.0}", separator, p_usage));
-                    separator = ", ";
-                    cpu_usage.detailed[i] = p_usage;
-                    avg += p_usage;
-                }
-                cpu_usage.avg = avg / count as f32;
+                cpu_usage.detailed = processors.iter().map(|p| p.get_cpu_usage()).collect();
+                cpu_usage.avg = cpu_usage.detailed.iter().sum::<f32>() / count as f32;
             }
 
             let mem_info = MemInfo {
@@ -140,263 +205,246 @@ async fn render_screen(engine_handle: EngineHandle) {
                 total_swap: system.get_total_swap(),
             };
 
-            fb.set_font_size(18.0);
-            fb.set_color(&Color {
-                red: 0xff as f64 / 256f64,
-                green: 0xbf as f64 / 256f64,
-                blue: 0.0,
-                alpha: 1.0,
-            });
-            fb.render_text(
-                &Point {
-                    x: x as f64,
-                    y: y as f64,
-                },
-                &format!(
-                    "CPU: {:>2.0}% [{}] ({:.1}°C)",
-                    cpu_usage.avg,
-                    &cpu_info_str,
-                    display::get_cpu_temperature()
-                ),
-            );
-            y += 18;
-
-            fb.set_color(&Color {
-                red: 1.0,
-                green: 0.0,
-                blue: 0.0,
-                alpha: 1.0,
-            });
-
-            fb.render_text(
-                &Point {
-                    x: x as f64,
-                    y: y as f64,
-                },
-                &format!(
-                    "Memory: {} / {}",
-                    size::Size::Kibibytes(mem_info.used_mem)
-                        .to_string(size::Base::Base2, size::Style::Smart),
-                    size::Size::Kibibytes(mem_info.total_mem)
-                        .to_string(size::Base::Base2, size::Style::Smart),
-                ),
-            );
-
             let _ = engine_handle
                 .send(EngineCmdData::SysInfo(AnnotatedSystemInfo {
                     source: engine::engine::DEFAULT_HOST.to_owned(),
                     si: SystemInfo {
-                        cpu: cpu_usage,
+                        cpu: cpu_usage.clone(),
                         mem: mem_info,
                     },
                 }))
                 .await;
 
-            {
-                y += 20;
-
-                fb.set_font_size(14.0);
-                fb.set_color(&Color {
-                    red: 0.5,
-                    green: 1.0,
-                    blue: 0.0,
-                    alpha: 1.0,
-                });
-
-                let secs = NET_REFRESH_TIMEOUT.as_secs() as i64;
-                let (prev, last) = engine_handle.last_net_info().await;
-                fb.render_text(
-                    &Point {
-                        x: x as f64,
-                        y: y as f64,
-                    },
-                    &format!(
-                        "Bytes tx: {}, tx/s: {}",
-                        size::Size::Bytes(last.tx_bytes)
-                            .to_string(size::Base::Base2, size::Style::Smart),
-                        size::Size::Bytes((last.tx_bytes - prev.tx_bytes) / secs)
-                            .to_string(size::Base::Base2, size::Style::Smart),
-                    ),
-                );
-                y += 14;
-
-                fb.set_color(&Color {
-                    red: 0.18,
-                    green: 0.56,
-                    blue: 0.83,
-                    alpha: 1.0,
-                });
-                fb.render_text(
-                    &Point {
-                        x: x as f64,
-                        y: y as f64,
-                    },
-                    &format!(
-                        "Bytes rx: {}, rx/s: {}",
-                        size::Size::Bytes(last.rx_bytes)
-                            .to_string(size::Base::Base2, size::Style::Smart),
-                        size::Size::Bytes((last.rx_bytes - prev.rx_bytes) / secs)
-                            .to_string(size::Base::Base2, size::Style::Smart),
-                    ),
-                );
-            }
-
-            {
-                fb.set_font_size(10.0);
-                let mut space = 0;
-                let touch_status = engine_handle.touch_info().await;
-                for msg in touch_status {
-                    y += space;
-                    if space == 0 {
-                        y += 22;
-                        space = 10;
-                    }
-                    fb.render_text(
-                        &Point {
-                            x: x as f64,
-                            y: y as f64,
-                        },
-                        &format!("Touched pins: {}", &print_touch_status(&msg)),
-                    );
+            // Theme chosen by an action replaces the configured one
+            let wanted_theme = engine_handle
+                .get_theme()
+                .await
+                .unwrap_or_else(|| default_theme.clone());
+            if wanted_theme != theme_name {
+                match themes.get(&wanted_theme) {
+                    Some(t) => theme = t.clone(),
+                    None => log::error!("Unknown theme {}", &wanted_theme),
                 }
+                theme_name = wanted_theme;
             }
 
-            y += 12;
+            let secs = NET_REFRESH_TIMEOUT.as_secs() as i64;
+            let (prev, last) = engine_handle.last_net_info().await;
+            let (net_tx, net_rx) = engine_handle.get_net_tx_rx(&NET_REFRESH_TIMEOUT).await;
+            let snapshot = widgets::Snapshot {
+                time: chrono::Local::now(),
+                cpu: cpu_usage,
+                cpu_temperature: display::get_cpu_temperature(),
+                mem: mem_info,
+                net: widgets::NetSnapshot {
+                    tx_bytes: last.tx_bytes,
+                    rx_bytes: last.rx_bytes,
+                    tx_rate: (last.tx_bytes - prev.tx_bytes) / secs,
+                    rx_rate: (last.rx_bytes - prev.rx_bytes) / secs,
+                },
+                touches: engine_handle.touch_info().await,
+                system_infos: engine_handle.get_system_infos().await,
+                net_tx,
+                net_rx,
+                pages: engine_handle.pages().await,
+                theme: theme.clone(),
+                host_colors: host_colors.clone(),
+            };
 
-            let layout = engine_handle.get_main_layout().await;
+            let current_layout = engine_handle.get_main_layout().await;
+            if current_layout != layout && screens.is_empty() {
+                layout = current_layout;
+                pages = build_pages(&screens, &hosts, layout);
+            }
 
-            {
-                use plotters::prelude::*;
-
-                let mut color_index: usize = 0;
-                {
-                    let mut cpu_axis_data = Vec::<SeriesData<Vec<f32>>>::new();
-                    let mut net_axis_data = Vec::<SeriesData<SummaryMemUsage>>::new();
-                    let mut max_net_data_count: u64 = 0;
-                    let (left_axis, right_axis) = {
-                        let sys_infos = engine_handle.get_system_infos().await;
-                        for (name, frb_si) in sys_infos.iter() {
-                            let cpu_usage: Vec<f32> = frb_si.iter().map(|x| x.cpu.avg).collect();
-                            let mem_data: Vec<MemInfo> = frb_si.iter().map(|x| x.mem).collect();
-
-                            cpu_axis_data.push(SeriesData {
-                                data: cpu_usage,
-                                name: name.to_owned(),
-                            });
-
-                            let smu = SummaryMemUsage {
-                                ram: mem_data.iter().map(|mu| mu.used_mem).collect(),
-                                swap: mem_data.iter().map(|mu| mu.used_swap).collect(),
-                                total_ram: mem_data[0].total_mem,
-                                total_swap: mem_data[0].total_swap,
-                            };
-                            max_net_data_count =
-                                max(max_net_data_count, *smu.ram.iter().max().unwrap());
-                            net_axis_data.push(SeriesData {
-                                data: smu,
-                                name: name.to_owned(),
-                            });
-                        }
+            fb.start();
+            fb.set_font(widgets::DEFAULT_FONT);
+            fb.set_color(&snapshot.theme.background);
+            fb.clean();
 
-                        (
-                            PlotData {
-                                data: cpu_axis_data,
-                                y_range: 0.0..100.0f32,
-                                formatter: |v| format!("{:.0}%", v),
-                            },
-                            PlotData {
-                                data: net_axis_data,
-                                y_range: 0..max_net_data_count,
-                                formatter: |v| {
-                                    size::Size::Kibibytes(*v)
-                                        .to_string(size::Base::Base2, size::Style::Smart)
-                                },
-                            },
-                        )
-                    };
+            // Whole screen is moved a bit from time to time against burn-in
+            let area = Rect {
+                x: shift as f64,
+                y: 0.0,
+                width: (fb.width() - shift) as f64,
+                height: fb.height() as f64,
+            };
+            let current = snapshot.pages.current().min(pages.len() - 1);
+            pages[current].1.render(&mut fb, &area, &snapshot);
 
-                    let plot = fb.get_backend().unwrap().into_drawing_area();
+            fb.finish();
+            sinks.publish(&fb);
 
-                    let plot = match layout {
-                        Layout::Horizontal => plot.margin(y + 2, 2, 2, (fb.width() / 2) as u32 + 2),
-                        Layout::Vertical => {
-                            plot.margin(y + 2, ((fb.height() - y as usize) / 2) as u32 + 2, 2, 2)
-                        }
-                    };
-                    helpers::plot_data(&plot, &WHITE, &mut color_index, left_axis, right_axis);
-                }
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = engine_handle.redraw_requested() => {}
+            };
+        }
+    }
 
-                {
-                    let (tx_data, rx_data) =
-                        engine_handle.get_net_tx_rx(&NET_REFRESH_TIMEOUT).await;
-                    if !tx_data.is_empty() && !rx_data.is_empty() {
-                        // Draw a network plot
-                        let plot = fb.get_backend().unwrap().into_drawing_area();
+    let display = match display {
+        DisplayKind::Auto => {
+            if config.framebuffer.is_some() || std::path::Path::new("/dev/fb1").exists() {
+                DisplayKind::Framebuffer
+            } else {
+                DisplayKind::Png
+            }
+        }
+        d => d,
+    };
 
-                        let plot = match layout {
-                            Layout::Horizontal => {
-                                plot.margin(y + 2, 2, (fb.width() / 2 + 2) as u32, 2)
-                            }
-                            Layout::Vertical => plot.margin(
-                                y + ((fb.height() - y as usize) / 2) as i32 + 2,
-                                2,
-                                2,
-                                2,
-                            ),
-                        };
-
-                        let tx_max: i64 = *tx_data.iter().max().unwrap();
-                        let rx_max: i64 = *rx_data.iter().max().unwrap();
-
-                        let left_axis = PlotData {
-                            data: vec![SeriesData {
-                                data: tx_data,
-                                name: "localhost".to_owned(),
-                            }],
-                            y_range: 0..tx_max,
-                            formatter: |v| {
-                                size::Size::Bytes(*v)
-                                    .to_string(size::Base::Base2, size::Style::Smart)
-                            },
-                        };
-                        let right_axis = PlotData {
-                            data: vec![SeriesData {
-                                data: rx_data,
-                                name: "localhost".to_owned(),
-                            }],
-                            y_range: 0..rx_max,
-                            formatter: |v| {
-                                size::Size::Bytes(*v)
-                                    .to_string(size::Base::Base2, size::Style::Smart)
-                            },
-                        };
-                        helpers::plot_data(&plot, &YELLOW, &mut color_index, left_axis, right_axis);
-                    }
+    let vnc = match config.vnc.as_ref() {
+        Some(vnc) => match VncServer::bind(vnc.listen, "fb4rasp").await {
+            Ok(mut server) => {
+                log::info!("VNC server listening on {}", server.local_addr());
+                if let Some(input) = server.take_input() {
+                    tokio::spawn(read_vnc_input(input, engine_handle.clone()));
                 }
+                Some(server)
             }
-
-            let events = fb.get_events();
-            for e in events {
-                log::debug!("Events {:?}", &e);
-                fb.render_text(
-                    &Point {
-                        x: e.position.x,
-                        y: e.position.y,
-                    },
-                    "X",
-                );
+            Err(e) => {
+                log::error!("Failed to start VNC server on {}: {}", vnc.listen, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let web = match config.http.as_ref() {
+        Some(http) => match web::WebServer::bind(http.listen).await {
+            Ok(server) => {
+                log::info!("HTTP server listening on {}", server.local_addr());
+                Some(server)
+            }
+            Err(e) => {
+                log::error!("Failed to start HTTP server on {}: {}", http.listen, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let sinks = FrameSinks { vnc, web };
+
+    let rotation = config.rotation();
+    let images = config.images.clone();
+    let fonts = config.fonts.clone();
+    let output = config.output.output().unwrap_or_default();
+    let default_theme = config.theme();
+    let touch_calibration = config.touch_calibration();
+    match display {
+        DisplayKind::Framebuffer => {
+            let path = config
+                .framebuffer
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_FRAMEBUFFER));
+            let mut fb = Fb4Rasp::new(path).unwrap();
+            fb.set_rotation(rotation);
+            if let Some(calibration) = touch_calibration {
+                fb.set_touch_calibration(calibration);
+            }
+            for reader in fb.take_input_readers() {
+                tokio::spawn(read_input(reader, engine_handle.clone()));
             }
+            render_screen_internal(
+                engine_handle,
+                fb,
+                images,
+                fonts,
+                default_theme,
+                content,
+                sinks,
+            )
+            .await
+        }
+        DisplayKind::Svg => {
+            let mut svg = CairoSvg::new(1920, 1080).unwrap();
+            svg.set_rotation(rotation);
+            svg.set_output(output);
+            render_screen_internal(
+                engine_handle,
+                svg,
+                images,
+                fonts,
+                default_theme,
+                content,
+                sinks,
+            )
+            .await
+        }
+        DisplayKind::Terminal => {
+            let term = CairoTerminal::new(TERMINAL_SIZE.0, TERMINAL_SIZE.1).unwrap();
+            tokio::spawn(redraw_on_resize(engine_handle.clone()));
+            render_screen_internal(
+                engine_handle,
+                term,
+                images,
+                fonts,
+                default_theme,
+                content,
+                sinks,
+            )
+            .await
+        }
+        _ => {
+            let mut img = CairoImage::new(1920, 1080).unwrap();
+            img.set_rotation(rotation);
+            img.set_snapshot_path(Some(PathBuf::from(PNG_SNAPSHOT_PATH)));
+            render_screen_internal(
+                engine_handle,
+                img,
+                images,
+                fonts,
+                default_theme,
+                content,
+                sinks,
+            )
+            .await
+        }
+    }
+}
 
-            fb.finish();
+/// Servers getting every finished frame
+struct FrameSinks {
+    vnc: Option<VncServer>,
+    web: Option<web::WebServer>,
+}
+
+impl FrameSinks {
+    fn publish<DB>(&self, fb: &DB)
+    where
+        for<'a> DB: Display<'a>,
+    {
+        if self.vnc.is_none() && self.web.is_none() {
+            return;
+        }
 
-            interval.tick().await;
+        let frame = match fb.frame() {
+            Some(frame) => frame,
+            None => return,
+        };
+        match (self.vnc.as_ref(), self.web.as_ref()) {
+            (Some(vnc), Some(web)) => {
+                web.publish(frame.clone());
+                vnc.publish(frame);
+            }
+            (Some(vnc), None) => vnc.publish(frame),
+            (None, Some(web)) => web.publish(frame),
+            (None, None) => {}
         }
     }
+}
 
-    if std::path::Path::new("/dev/fb1").exists() {
-        render_screen_internal(engine_handle, Fb4Rasp::new().unwrap()).await;
-    } else {
-        render_screen_internal(engine_handle, CairoSvg::new(1920, 1080).unwrap()).await;
+/// Terminal is resized when the process gets SIGWINCH
+async fn redraw_on_resize(engine_handle: EngineHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut resized = match signal(SignalKind::window_change()) {
+        Ok(s) => s,
+        Err(e) => {
+            log::warn!("Failed to listen for terminal resizes: {}", e);
+            return;
+        }
+    };
+    while resized.recv().await.is_some() {
+        engine_handle.request_redraw();
     }
 }
 
@@ -506,6 +554,59 @@ async fn update_touch_status(mut engine_handle: EngineHandle) {
     }
 }
 
+fn to_input_event(e: &display::Event) -> Option<InputEvent> {
+    let gesture = match e.what {
+        EventType::Touched => Gesture::Touched,
+        EventType::Tap => Gesture::Tap,
+        EventType::LongPress => Gesture::LongPress,
+        EventType::SwipeLeft => Gesture::SwipeLeft,
+        EventType::SwipeRight => Gesture::SwipeRight,
+        EventType::SwipeUp => Gesture::SwipeUp,
+        EventType::SwipeDown => Gesture::SwipeDown,
+        EventType::KeyDown(code) => return Some(InputEvent::Key(code)),
+        EventType::Rotate(steps) => return Some(InputEvent::Rotate(steps)),
+        // Continuous gestures aren't bound to actions
+        EventType::Drag { .. }
+        | EventType::Pinch { .. }
+        | EventType::ContactDown(_)
+        | EventType::ContactMove(_)
+        | EventType::ContactUp(_)
+        | EventType::KeyUp(_) => return None,
+    };
+
+    Some(InputEvent::Touch {
+        gesture,
+        x: e.position.x,
+        y: e.position.y,
+    })
+}
+
+async fn read_input(mut reader: InputReader, mut engine_handle: EngineHandle) {
+    log::debug!("Reading input events of {}", reader.name());
+    loop {
+        match reader.next_events().await {
+            Ok(events) => {
+                for e in events.iter().filter_map(to_input_event) {
+                    engine_handle.send(EngineCmdData::Input(e)).await;
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to read input device {}: {}", reader.name(), e);
+                return;
+            }
+        }
+    }
+}
+
+/// Pointer presses of VNC clients are touches
+async fn read_vnc_input(mut input: VncInput, mut engine_handle: EngineHandle) {
+    while let Some(events) = input.next_events().await {
+        for e in events.iter().filter_map(to_input_event) {
+            engine_handle.send(EngineCmdData::Input(e)).await;
+        }
+    }
+}
+
 fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
     use http::uri::Uri;
     enum Session {
@@ -513,7 +614,8 @@ fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
         Connected((WsSession, Uri)),
     }
 
-    async fn handle_session(mut session: Session, mut engine_handle: EngineHandle) {
+    /// Readings are annotated with `name` of the remote in the config
+    async fn handle_session(name: String, mut session: Session, mut engine_handle: EngineHandle) {
         loop {
             // Use refrence so in case of the (hopefully) most common case (i.e. connected and got msg),
             // nothing need to be done
@@ -532,7 +634,7 @@ fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
                             );
                             let _ = engine_handle
                                 .send(EngineCmdData::SysInfo(AnnotatedSystemInfo {
-                                    source: address.host().unwrap().to_owned(),
+                                    source: name.clone(),
                                     si: fb4rasp_shared::SystemInfo::default(),
                                 }))
                                 .await;
@@ -543,7 +645,7 @@ fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
                         log::error!("Failed to connect to {} due {:?}", &address, &e);
                         let _ = engine_handle
                             .send(EngineCmdData::SysInfo(AnnotatedSystemInfo {
-                                source: address.host().unwrap().to_owned(),
+                                source: name.clone(),
                                 si: fb4rasp_shared::SystemInfo::default(),
                             }))
                             .await;
@@ -561,7 +663,7 @@ fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
                                     // TODO: Ignore errors for now
                                     let _ = engine_handle
                                         .send(EngineCmdData::SysInfo(AnnotatedSystemInfo {
-                                            source: addr.host().unwrap().to_owned(),
+                                            source: name.clone(),
                                             si: d,
                                         }))
                                         .await;
@@ -588,6 +690,7 @@ fn get_remote_sys_data(engine_handle: EngineHandle, config: config::Config) {
             .build()
             .unwrap();
         tokio::spawn(handle_session(
+            r.0.clone(),
             Session::Unconnected(uri),
             engine_handle.clone(),
         ));
@@ -611,11 +714,49 @@ async fn main() {
     let cmd_line_opt = CmdLineOptions::from_args();
     log::debug!("Parsed cmd line parameters:\n{:#?}", &cmd_line_opt);
 
-    let config_file = if cmd_line_opt.config.is_some() {
-        config::read_toml_config(cmd_line_opt.config.unwrap()).unwrap()
+    if let Some(Command::ListDisplays) = cmd_line_opt.command {
+        for info in Fb4Rasp::list() {
+            println!("{}", info);
+        }
+        return;
+    }
+
+    let mut config_file = if let Some(path) = cmd_line_opt.config.as_ref() {
+        config::read_toml_config(path).unwrap()
     } else {
         config::Config::new()
     };
+    if cmd_line_opt.framebuffer.is_some() {
+        config_file.display.framebuffer = cmd_line_opt.framebuffer;
+    }
+
+    if let Some(Command::Calibrate) = cmd_line_opt.command {
+        let path = config_file
+            .display
+            .framebuffer
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_FRAMEBUFFER));
+        let mut fb = Fb4Rasp::new(path).unwrap();
+        let calibration = tokio::select! {
+            c = calibrate::calibrate(&mut fb) => c,
+            _ = handle_ctrl_c() => return,
+        };
+        match (calibration, cmd_line_opt.config) {
+            (Some(c), Some(path)) => match config::write_calibration(&path, &c) {
+                Ok(()) => log::info!("Calibration stored in {:?}", &path),
+                Err(e) => log::error!("{}", e),
+            },
+            (Some(c), None) => println!("[display]\ncalibration = {:?}", c.matrix),
+            (None, _) => log::error!("Calibration failed, touches are not spread enough"),
+        }
+        return;
+    }
+    let display_config = config_file.display.clone();
+    let content = ScreenContent {
+        screens: config_file.screens.clone(),
+        hosts: config_file.hosts(),
+        themes: config_file.themes.clone(),
+        host_colors: config_file.host_colors(),
+    };
 
     let mut engine_handle = EngineHandle::default();
     {
@@ -627,29 +768,25 @@ async fn main() {
         powerdown_rule.add_action(Box::new(action::ShutdownAction {}));
         engine_handle.add_rule(powerdown_rule).await;
 
-        struct ChangeLayoutAction {}
-        impl action::Action for ChangeLayoutAction {
-            fn apply(&self, params: &mut Parameters) -> bool {
-                match params.options.main_layout {
-                    Layout::Vertical => params.options.main_layout = Layout::Horizontal,
-                    Layout::Horizontal => params.options.main_layout = Layout::Vertical,
-                }
-                true
-            }
-        }
-
         let swap_layout_rule = Box::new(rule::SimpleRule::new(
             Box::new(condition::OneItemCondition::new(2)),
-            Box::new(ChangeLayoutAction {}),
+            Box::new(action::ToggleLayoutAction {}),
         ));
         engine_handle.add_rule(swap_layout_rule).await;
+
+        engine_handle
+            .send(EngineCmdData::SetKeyMap(config_file.key_map()))
+            .await;
+        if let Some(carousel) = config_file.carousel.as_ref() {
+            engine_handle.set_carousel(carousel.carousel()).await;
+        }
     }
 
     get_remote_sys_data(engine_handle.clone(), config_file);
     tokio::spawn(update_touch_status(engine_handle.clone()));
 
     tokio::select! {
-        _ = {render_screen(engine_handle.clone())} => {}
+        _ = {render_screen(engine_handle.clone(), cmd_line_opt.display, display_config, content)} => {}
         _ = {get_router_net_stats(engine_handle)} => {}
         _ = handle_ctrl_c() => {}
     };
@@ -0,0 +1,591 @@
+use crate::layout::{Direction, Grid, Size, Stack, Widget};
+use crate::widgets::{
+    Alerts, Chart, ChartKind, Clock, CpuLine, MemLine, NetCounters, PageIndicator, TextStyle,
+    TouchStatus,
+};
+use display::{Color, Display};
+use serde::Deserialize;
+use toml::Spanned;
+
+/// Widget as written in the config file, the root one of every `[[screen]]`
+/// also has a name.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetConfig {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    name: Option<Spanned<String>>,
+
+    // Share of the parent row or column, `auto` if neither is set
+    weight: Option<Spanned<f64>>,
+    fixed: Option<Spanned<f64>>,
+
+    // Containers
+    padding: Option<f64>,
+    spacing: Option<f64>,
+    columns: Option<Spanned<Vec<f64>>>,
+    rows: Option<Spanned<Vec<f64>>>,
+    #[serde(default)]
+    children: Vec<WidgetConfig>,
+
+    // Text
+    font: Option<String>,
+    font_size: Option<f64>,
+    color: Option<Spanned<String>>,
+    format: Option<String>,
+    host: Option<Spanned<String>>,
+
+    // Network counters
+    tx_color: Option<Spanned<String>>,
+    rx_color: Option<Spanned<String>>,
+    icon: Option<String>,
+
+    // Charts
+    chart: Option<Spanned<String>>,
+    hosts: Option<Spanned<Vec<String>>>,
+}
+
+/// Invalid screen layout, `line` and `column` start at 1.
+#[derive(Debug, PartialEq)]
+pub struct LayoutError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+/// Widget tree checked against the config, can be built for any display.
+#[derive(Debug, Clone)]
+pub enum WidgetSpec {
+    Stack {
+        direction: Direction,
+        padding: f64,
+        spacing: f64,
+        children: Vec<(Size, WidgetSpec)>,
+    },
+    Grid {
+        columns: Vec<f64>,
+        rows: Vec<f64>,
+        padding: f64,
+        spacing: f64,
+        cells: Vec<WidgetSpec>,
+    },
+    Clock {
+        format: Option<String>,
+        style: TextStyle,
+    },
+    Cpu {
+        host: Option<String>,
+        style: TextStyle,
+    },
+    Memory {
+        host: Option<String>,
+        style: TextStyle,
+    },
+    Network {
+        tx: TextStyle,
+        rx: TextStyle,
+        icon: Option<String>,
+    },
+    Touch {
+        style: TextStyle,
+    },
+    Alerts {
+        style: TextStyle,
+    },
+    Chart {
+        kind: ChartKind,
+        color: Option<Color>,
+        hosts: Option<Vec<String>>,
+    },
+    Pages {
+        color: Option<Color>,
+    },
+}
+
+impl WidgetSpec {
+    pub fn build<DB>(&self) -> Box<dyn Widget<DB>>
+    where
+        for<'a> DB: Display<'a> + 'static,
+    {
+        match self {
+            WidgetSpec::Stack {
+                direction,
+                padding,
+                spacing,
+                children,
+            } => {
+                let mut stack = Stack::new(*direction, *padding, *spacing);
+                for (size, child) in children.iter() {
+                    stack.add(*size, child.build());
+                }
+                Box::new(stack)
+            }
+            WidgetSpec::Grid {
+                columns,
+                rows,
+                padding,
+                spacing,
+                cells,
+            } => {
+                let mut grid = Grid::new(columns.clone(), rows.clone(), *padding, *spacing);
+                for cell in cells.iter() {
+                    grid.add(cell.build());
+                }
+                Box::new(grid)
+            }
+            WidgetSpec::Clock { format, style } => {
+                let mut clock = Clock {
+                    style: style.clone(),
+                    ..Clock::default()
+                };
+                if let Some(format) = format {
+                    clock.format = format.clone();
+                }
+                Box::new(clock)
+            }
+            WidgetSpec::Cpu { host, style } => Box::new(CpuLine {
+                host: host.clone(),
+                style: style.clone(),
+            }),
+            WidgetSpec::Memory { host, style } => Box::new(MemLine {
+                host: host.clone(),
+                style: style.clone(),
+            }),
+            WidgetSpec::Network { tx, rx, icon } => Box::new(NetCounters {
+                tx: tx.clone(),
+                rx: rx.clone(),
+                icon: icon.clone(),
+            }),
+            WidgetSpec::Touch { style } => Box::new(TouchStatus {
+                style: style.clone(),
+            }),
+            WidgetSpec::Alerts { style } => Box::new(Alerts {
+                style: style.clone(),
+            }),
+            WidgetSpec::Chart { kind, color, hosts } => {
+                let mut chart = Chart::new(*kind);
+                chart.color = color.clone();
+                chart.hosts = hosts.clone();
+                Box::new(chart)
+            }
+            WidgetSpec::Pages { color } => Box::new(PageIndicator {
+                color: color.clone(),
+                ..PageIndicator::default()
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Screen {
+    pub name: String,
+    pub root: WidgetSpec,
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`
+pub fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .map(|c| u8::from_str_radix(c, 16).unwrap() as f64 / 255.0)
+    };
+    Some(Color {
+        red: channel(0)?,
+        green: channel(2)?,
+        blue: channel(4)?,
+        alpha: channel(6).unwrap_or(1.0),
+    })
+}
+
+/// Checks screens of the config, `source` is the config file content used to
+/// locate errors and `hosts` are names valid as data sources.
+pub fn parse_screens(
+    configs: &[WidgetConfig],
+    source: &str,
+    hosts: &[String],
+) -> Result<Vec<Screen>, LayoutError> {
+    let parser = Parser { source, hosts };
+    let mut screens: Vec<Screen> = Vec::new();
+    for config in configs.iter() {
+        let name = match &config.name {
+            Some(name) => name,
+            None => return Err(parser.error(&config.kind, "screen without a name")),
+        };
+        if screens.iter().any(|s| &s.name == name.get_ref()) {
+            return Err(parser.error(name, format!("duplicate screen '{}'", name.get_ref())));
+        }
+
+        screens.push(Screen {
+            name: name.get_ref().clone(),
+            root: parser.widget(config)?,
+        });
+    }
+
+    Ok(screens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    hosts: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+    fn error<T, M: Into<String>>(&self, at: &Spanned<T>, message: M) -> LayoutError {
+        let before = &self.source[..at.start().min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        LayoutError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn color(&self, value: &Option<Spanned<String>>) -> Result<Option<Color>, LayoutError> {
+        match value {
+            Some(v) => parse_color(v.get_ref())
+                .map(Some)
+                .ok_or_else(|| self.error(v, format!("invalid color '{}'", v.get_ref()))),
+            None => Ok(None),
+        }
+    }
+
+    fn host(&self, host: &Spanned<String>) -> Result<String, LayoutError> {
+        if self.hosts.contains(host.get_ref()) {
+            Ok(host.get_ref().clone())
+        } else {
+            Err(self.error(host, format!("unknown host '{}'", host.get_ref())))
+        }
+    }
+
+    /// Style of the widget, based on the default one of its type, colors
+    /// which aren't given are taken from the theme
+    fn style(
+        &self,
+        config: &WidgetConfig,
+        color: &Option<Spanned<String>>,
+        default: &TextStyle,
+    ) -> Result<TextStyle, LayoutError> {
+        Ok(TextStyle {
+            font: config.font.clone().unwrap_or_else(|| default.font.clone()),
+            font_size: config.font_size.unwrap_or(default.font_size),
+            color: self.color(color)?.or_else(|| default.color.clone()),
+        })
+    }
+
+    fn size(&self, config: &WidgetConfig) -> Result<Size, LayoutError> {
+        match (&config.weight, &config.fixed) {
+            (Some(_), Some(fixed)) => Err(self.error(fixed, "both weight and fixed size given")),
+            (Some(weight), None) if *weight.get_ref() > 0.0 => Ok(Size::Weight(*weight.get_ref())),
+            (Some(weight), None) => Err(self.error(weight, "weight must be positive")),
+            (None, Some(fixed)) if *fixed.get_ref() >= 0.0 => Ok(Size::Fixed(*fixed.get_ref())),
+            (None, Some(fixed)) => Err(self.error(fixed, "fixed size can't be negative")),
+            (None, None) => Ok(Size::Auto),
+        }
+    }
+
+    /// Rejects settings which don't apply to the widget type
+    fn unexpected(&self, config: &WidgetConfig, allowed: &[&str]) -> Result<(), LayoutError> {
+        let given = [
+            ("padding", config.padding.is_some()),
+            ("spacing", config.spacing.is_some()),
+            ("columns", config.columns.is_some()),
+            ("rows", config.rows.is_some()),
+            ("children", !config.children.is_empty()),
+            ("font", config.font.is_some()),
+            ("font_size", config.font_size.is_some()),
+            ("color", config.color.is_some()),
+            ("format", config.format.is_some()),
+            ("host", config.host.is_some()),
+            ("tx_color", config.tx_color.is_some()),
+            ("rx_color", config.rx_color.is_some()),
+            ("icon", config.icon.is_some()),
+            ("chart", config.chart.is_some()),
+            ("hosts", config.hosts.is_some()),
+        ];
+        match given
+            .iter()
+            .find(|(name, set)| *set && !allowed.contains(name))
+        {
+            Some((name, _)) => Err(self.error(
+                &config.kind,
+                format!("'{}' does not apply to {}", name, config.kind.get_ref()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn widget(&self, config: &WidgetConfig) -> Result<WidgetSpec, LayoutError> {
+        const TEXT: &[&str] = &["font", "font_size", "color"];
+        const CONTAINER: &[&str] = &["padding", "spacing", "children"];
+
+        let allowed = |extra: &[&'static str], base: &[&'static str]| -> Vec<&'static str> {
+            extra.iter().chain(base.iter()).copied().collect()
+        };
+
+        if let Some(name) = config.children.iter().find_map(|c| c.name.as_ref()) {
+            return Err(self.error(name, "only screens have a name"));
+        }
+
+        let spec = match config.kind.get_ref().as_str() {
+            kind @ "row" | kind @ "column" => {
+                self.unexpected(config, CONTAINER)?;
+                let mut children = Vec::with_capacity(config.children.len());
+                for child in config.children.iter() {
+                    children.push((self.size(child)?, self.widget(child)?));
+                }
+                WidgetSpec::Stack {
+                    direction: if kind == "row" {
+                        Direction::Horizontal
+                    } else {
+                        Direction::Vertical
+                    },
+                    padding: config.padding.unwrap_or(0.0),
+                    spacing: config.spacing.unwrap_or(0.0),
+                    children,
+                }
+            }
+            "grid" => {
+                self.unexpected(config, &allowed(&["columns", "rows"], CONTAINER))?;
+                let weights = |value: &Option<Spanned<Vec<f64>>>, name: &str| match value {
+                    Some(v) if !v.get_ref().is_empty() && v.get_ref().iter().all(|w| *w > 0.0) => {
+                        Ok(v.get_ref().clone())
+                    }
+                    Some(v) => {
+                        Err(self.error(v, format!("{} must be a list of positive weights", name)))
+                    }
+                    None => Err(self.error(&config.kind, format!("grid without {}", name))),
+                };
+                let columns = weights(&config.columns, "columns")?;
+                let rows = weights(&config.rows, "rows")?;
+                if config.children.len() > columns.len() * rows.len() {
+                    let extra = &config.children[columns.len() * rows.len()];
+                    return Err(self.error(&extra.kind, "more widgets than grid cells"));
+                }
+
+                let mut cells = Vec::with_capacity(config.children.len());
+                for child in config.children.iter() {
+                    if let Some(size) = child.weight.as_ref().or(child.fixed.as_ref()) {
+                        return Err(self.error(size, "grid cells can't be sized"));
+                    }
+                    cells.push(self.widget(child)?);
+                }
+                WidgetSpec::Grid {
+                    columns,
+                    rows,
+                    padding: config.padding.unwrap_or(0.0),
+                    spacing: config.spacing.unwrap_or(0.0),
+                    cells,
+                }
+            }
+            "clock" => {
+                self.unexpected(config, &allowed(&["format"], TEXT))?;
+                WidgetSpec::Clock {
+                    format: config.format.clone(),
+                    style: self.style(config, &config.color, &Clock::default().style)?,
+                }
+            }
+            "cpu" => {
+                self.unexpected(config, &allowed(&["host"], TEXT))?;
+                WidgetSpec::Cpu {
+                    host: config.host.as_ref().map(|h| self.host(h)).transpose()?,
+                    style: self.style(config, &config.color, &CpuLine::default().style)?,
+                }
+            }
+            "memory" => {
+                self.unexpected(config, &allowed(&["host"], TEXT))?;
+                WidgetSpec::Memory {
+                    host: config.host.as_ref().map(|h| self.host(h)).transpose()?,
+                    style: self.style(config, &config.color, &MemLine::default().style)?,
+                }
+            }
+            "network" => {
+                self.unexpected(
+                    config,
+                    &["font", "font_size", "tx_color", "rx_color", "icon"],
+                )?;
+                let default = NetCounters::default();
+                WidgetSpec::Network {
+                    tx: self.style(config, &config.tx_color, &default.tx)?,
+                    rx: self.style(config, &config.rx_color, &default.rx)?,
+                    icon: config.icon.clone(),
+                }
+            }
+            "touch" => {
+                self.unexpected(config, TEXT)?;
+                WidgetSpec::Touch {
+                    style: self.style(config, &config.color, &TouchStatus::default().style)?,
+                }
+            }
+            "alerts" => {
+                self.unexpected(config, TEXT)?;
+                WidgetSpec::Alerts {
+                    style: self.style(config, &config.color, &Alerts::default().style)?,
+                }
+            }
+            "chart" => {
+                self.unexpected(config, &["chart", "color", "hosts"])?;
+                let kind = match &config.chart {
+                    Some(c) if c.get_ref() == "system" => ChartKind::SystemLoad,
+                    Some(c) if c.get_ref() == "network" => ChartKind::Network,
+                    Some(c) => {
+                        return Err(self.error(
+                            c,
+                            format!(
+                                "unknown chart '{}', expected system or network",
+                                c.get_ref()
+                            ),
+                        ))
+                    }
+                    None => return Err(self.error(&config.kind, "chart without a chart type")),
+                };
+                let hosts = match &config.hosts {
+                    Some(hosts) if kind != ChartKind::SystemLoad => {
+                        return Err(self.error(hosts, "only system charts show hosts"))
+                    }
+                    Some(hosts) => match hosts.get_ref().iter().find(|h| !self.hosts.contains(h)) {
+                        Some(h) => return Err(self.error(hosts, format!("unknown host '{}'", h))),
+                        None => Some(hosts.get_ref().clone()),
+                    },
+                    None => None,
+                };
+                WidgetSpec::Chart {
+                    kind,
+                    color: self.color(&config.color)?,
+                    hosts,
+                }
+            }
+            "pages" => {
+                self.unexpected(config, &["color"])?;
+                WidgetSpec::Pages {
+                    color: self.color(&config.color)?,
+                }
+            }
+            other => {
+                return Err(self.error(
+                    &config.kind,
+                    format!(
+                        "unknown widget type '{}', expected one of row, column, grid, clock, \
+                         cpu, memory, network, touch, alerts, chart or pages",
+                        other
+                    ),
+                ))
+            }
+        };
+
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Screens {
+        screen: Vec<WidgetConfig>,
+    }
+
+    fn parse(source: &str) -> Result<Vec<Screen>, LayoutError> {
+        let screens: Screens = toml::from_str(source).unwrap();
+        parse_screens(&screens.screen, source, &["localhost".to_owned()])
+    }
+
+    #[test]
+    fn screens_are_parsed() {
+        let screens = parse(
+            r##"
+[[screen]]
+name = "overview"
+type = "column"
+padding = 2
+
+[[screen.children]]
+type = "clock"
+color = "#ff000080"
+
+[[screen.children]]
+type = "grid"
+columns = [1, 2]
+rows = [1]
+weight = 1
+
+[[screen.children.children]]
+type = "chart"
+chart = "system"
+hosts = ["localhost"]
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(1, screens.len());
+        assert_eq!("overview", screens[0].name);
+        match &screens[0].root {
+            WidgetSpec::Stack {
+                direction: Direction::Vertical,
+                children,
+                ..
+            } => {
+                assert_eq!(Size::Auto, children[0].0);
+                assert_eq!(Size::Weight(1.0), children[1].0);
+                match &children[0].1 {
+                    WidgetSpec::Clock { style, .. } => {
+                        let color = style.color.as_ref().unwrap();
+                        assert_eq!(1.0, color.red);
+                        assert_eq!(128.0 / 255.0, color.alpha);
+                    }
+                    other => panic!("Unexpected {:?}", other),
+                }
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_are_located() {
+        let error = |source| parse(source).unwrap_err();
+
+        assert_eq!(
+            LayoutError {
+                line: 5,
+                column: 8,
+                message: "unknown widget type 'clok', expected one of row, column, grid, \
+                          clock, cpu, memory, network, touch, alerts, chart or pages"
+                    .to_owned(),
+            },
+            error(
+                "[[screen]]\nname = \"a\"\ntype = \"row\"\n[[screen.children]]\ntype = \"clok\"\n"
+            )
+        );
+        let e = error("[[screen]]\nname = \"a\"\ntype = \"cpu\"\nhost = \"nas\"\n");
+        assert_eq!(
+            (4, 8, "unknown host 'nas'"),
+            (e.line, e.column, e.message.as_str())
+        );
+        assert_eq!(
+            "invalid color 'red'",
+            error("[[screen]]\nname = \"a\"\ntype = \"clock\"\ncolor = \"red\"\n").message
+        );
+        assert_eq!(
+            "'children' does not apply to clock",
+            error(
+                "[[screen]]\nname = \"a\"\ntype = \"clock\"\n[[screen.children]]\ntype = \"cpu\"\n"
+            )
+            .message
+        );
+    }
+}
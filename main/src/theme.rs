@@ -0,0 +1,368 @@
+use crate::screens::parse_color;
+use display::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub const DEFAULT_THEME: &str = "dark";
+
+/// Colors of parts of the UI by their meaning, widgets without a color of
+/// their own use these.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub background: Color,
+    pub clock: Color,
+    pub cpu: Color,
+    pub mem: Color,
+    pub tx: Color,
+    pub rx: Color,
+    pub touch: Color,
+    /// Axis lines of charts
+    pub axis: Color,
+    /// Axis labels of charts
+    pub labels: Color,
+    pub legend: Color,
+    pub alert: Color,
+    /// Page indicator
+    pub pages: Color,
+    /// Chart series, e.g. a color per host
+    pub series: Vec<Color>,
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> Color {
+    rgba(red, green, blue, 1.0)
+}
+
+fn rgba(red: u8, green: u8, blue: u8, alpha: f64) -> Color {
+    Color {
+        red: red as f64 / 255.0,
+        green: green as f64 / 255.0,
+        blue: blue as f64 / 255.0,
+        alpha,
+    }
+}
+
+fn dark() -> Theme {
+    Theme {
+        background: rgb(0, 0, 0),
+        clock: rgb(230, 230, 230),
+        cpu: rgb(255, 191, 0),
+        mem: rgb(255, 0, 0),
+        tx: rgb(128, 255, 0),
+        rx: rgb(46, 143, 212),
+        touch: rgb(46, 143, 212),
+        axis: rgb(255, 0, 0),
+        labels: rgb(255, 255, 255),
+        legend: rgba(200, 200, 200, 0.6),
+        alert: rgb(255, 64, 64),
+        pages: rgb(230, 230, 230),
+        series: vec![
+            rgb(230, 25, 75),
+            rgb(60, 180, 75),
+            rgb(255, 225, 25),
+            rgb(0, 130, 200),
+            rgb(245, 130, 48),
+            rgb(145, 30, 180),
+            rgb(70, 240, 240),
+            rgb(240, 50, 230),
+        ],
+    }
+}
+
+fn light() -> Theme {
+    Theme {
+        background: rgb(255, 255, 255),
+        clock: rgb(25, 25, 25),
+        cpu: rgb(179, 102, 0),
+        mem: rgb(191, 0, 0),
+        tx: rgb(51, 140, 0),
+        rx: rgb(25, 89, 166),
+        touch: rgb(25, 89, 166),
+        axis: rgb(102, 102, 102),
+        labels: rgb(25, 25, 25),
+        legend: rgba(60, 60, 60, 0.8),
+        alert: rgb(217, 0, 0),
+        pages: rgb(77, 77, 77),
+        series: vec![
+            rgb(200, 20, 60),
+            rgb(30, 130, 50),
+            rgb(0, 90, 170),
+            rgb(210, 100, 0),
+            rgb(120, 20, 150),
+            rgb(0, 128, 128),
+            rgb(150, 110, 0),
+            rgb(200, 30, 180),
+        ],
+    }
+}
+
+fn high_contrast() -> Theme {
+    Theme {
+        background: rgb(0, 0, 0),
+        clock: rgb(255, 255, 255),
+        cpu: rgb(255, 255, 0),
+        mem: rgb(255, 0, 255),
+        tx: rgb(0, 255, 0),
+        rx: rgb(0, 255, 255),
+        touch: rgb(255, 255, 255),
+        axis: rgb(255, 255, 255),
+        labels: rgb(255, 255, 255),
+        legend: rgb(255, 255, 255),
+        alert: rgb(255, 0, 0),
+        pages: rgb(255, 255, 255),
+        series: vec![
+            rgb(255, 255, 0),
+            rgb(0, 255, 255),
+            rgb(255, 0, 255),
+            rgb(0, 255, 0),
+            rgb(255, 128, 0),
+            rgb(255, 255, 255),
+        ],
+    }
+}
+
+/// Only reds, dimmed, to keep eyes adapted to darkness
+fn night_red() -> Theme {
+    Theme {
+        background: rgb(0, 0, 0),
+        clock: rgb(200, 0, 0),
+        cpu: rgb(170, 0, 0),
+        mem: rgb(140, 0, 0),
+        tx: rgb(170, 0, 0),
+        rx: rgb(120, 0, 0),
+        touch: rgb(120, 0, 0),
+        axis: rgb(90, 0, 0),
+        labels: rgb(150, 0, 0),
+        legend: rgb(120, 0, 0),
+        alert: rgb(255, 0, 0),
+        pages: rgb(120, 0, 0),
+        series: vec![
+            rgb(220, 0, 0),
+            rgb(150, 20, 20),
+            rgb(255, 60, 30),
+            rgb(110, 0, 0),
+        ],
+    }
+}
+
+/// Themes available without any configuration
+pub fn builtin_themes() -> BTreeMap<String, Theme> {
+    vec![
+        ("dark", dark()),
+        ("light", light()),
+        ("high-contrast", high_contrast()),
+        ("night-red", night_red()),
+    ]
+    .into_iter()
+    .map(|(name, theme)| (name.to_owned(), theme))
+    .collect()
+}
+
+/// Theme as written in the config file, colors are `#rrggbb[aa]`. Colors
+/// which aren't given are taken from the `base` theme, or from the built-in
+/// theme with the same name (or the default one).
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeConfig {
+    base: Option<String>,
+    background: Option<String>,
+    clock: Option<String>,
+    cpu: Option<String>,
+    mem: Option<String>,
+    tx: Option<String>,
+    rx: Option<String>,
+    touch: Option<String>,
+    axis: Option<String>,
+    labels: Option<String>,
+    legend: Option<String>,
+    alert: Option<String>,
+    pages: Option<String>,
+    series: Option<Vec<String>>,
+}
+
+impl ThemeConfig {
+    fn apply(&self, name: &str, theme: &mut Theme) -> Result<(), String> {
+        let color = |slot: &str, value: &str| {
+            parse_color(value)
+                .ok_or_else(|| format!("Invalid {} color '{}' in theme {}", slot, value, name))
+        };
+
+        let slots = [
+            ("background", &self.background, &mut theme.background),
+            ("clock", &self.clock, &mut theme.clock),
+            ("cpu", &self.cpu, &mut theme.cpu),
+            ("mem", &self.mem, &mut theme.mem),
+            ("tx", &self.tx, &mut theme.tx),
+            ("rx", &self.rx, &mut theme.rx),
+            ("touch", &self.touch, &mut theme.touch),
+            ("axis", &self.axis, &mut theme.axis),
+            ("labels", &self.labels, &mut theme.labels),
+            ("legend", &self.legend, &mut theme.legend),
+            ("alert", &self.alert, &mut theme.alert),
+            ("pages", &self.pages, &mut theme.pages),
+        ];
+        for (slot, value, target) in slots {
+            if let Some(value) = value {
+                *target = color(slot, value)?;
+            }
+        }
+
+        if let Some(series) = &self.series {
+            if series.is_empty() {
+                return Err(format!("No series colors in theme {}", name));
+            }
+            theme.series = series
+                .iter()
+                .map(|value| color("series", value))
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Built-in themes with the configured ones added or changed
+pub fn parse_themes(
+    configs: &BTreeMap<String, ThemeConfig>,
+) -> Result<BTreeMap<String, Theme>, String> {
+    let builtin = builtin_themes();
+    let mut themes = builtin.clone();
+    for (name, config) in configs.iter() {
+        let base = config.base.as_deref().unwrap_or_else(|| {
+            if builtin.contains_key(name) {
+                name
+            } else {
+                DEFAULT_THEME
+            }
+        });
+        let mut theme = builtin
+            .get(base)
+            .cloned()
+            .ok_or_else(|| format!("Unknown base theme {} of theme {}", base, name))?;
+        config.apply(name, &mut theme)?;
+        themes.insert(name.clone(), theme);
+    }
+
+    Ok(themes)
+}
+
+/// Colors of hosts in charts, the same for every render and restart. Hosts
+/// get series colors of the theme in order (the local one first, then remotes
+/// by name) unless they have their own.
+#[derive(Debug, Clone, Default)]
+pub struct HostColors {
+    /// Local host first, then remotes
+    hosts: Vec<String>,
+    overrides: BTreeMap<String, Color>,
+}
+
+impl HostColors {
+    pub fn new(hosts: Vec<String>, overrides: BTreeMap<String, Color>) -> Self {
+        Self { hosts, overrides }
+    }
+
+    pub fn color(&self, host: &str, theme: &Theme) -> Color {
+        if let Some(color) = self.overrides.get(host) {
+            return color.clone();
+        }
+
+        // Unknown hosts by a hash of their name, which is the same on any
+        // platform and for any configured hosts
+        let index = self
+            .hosts
+            .iter()
+            .position(|h| h == host)
+            .unwrap_or_else(|| {
+                host.bytes()
+                    .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32))
+                    as usize
+            });
+        match theme.series.len() {
+            0 => theme.labels.clone(),
+            len => theme.series[index % len].clone(),
+        }
+    }
+
+    /// Sorts host names in the order of their colors, unknown ones
+    /// alphabetically at the end
+    pub fn sort<T: AsRef<str>>(&self, hosts: &mut [T]) {
+        hosts.sort_by_key(|h| {
+            let position = self.hosts.iter().position(|c| c == h.as_ref());
+            (position.unwrap_or(self.hosts.len()), h.as_ref().to_owned())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_are_configured() {
+        let configs: BTreeMap<String, ThemeConfig> = toml::from_str(
+            r##"
+[light]
+clock = "#ff0000"
+
+[mine]
+base = "night-red"
+series = ["#00ff00"]
+"##,
+        )
+        .unwrap();
+        let themes = parse_themes(&configs).unwrap();
+
+        assert_eq!(1.0, themes["light"].clock.red);
+        assert_eq!(light().cpu.red, themes["light"].cpu.red);
+        assert_eq!(night_red().clock.red, themes["mine"].clock.red);
+        assert_eq!(1, themes["mine"].series.len());
+        assert!(themes.contains_key("high-contrast"));
+
+        let configs: BTreeMap<String, ThemeConfig> =
+            toml::from_str("[mine]\naxis = \"red\"\n").unwrap();
+        assert_eq!(
+            "Invalid axis color 'red' in theme mine",
+            parse_themes(&configs).unwrap_err()
+        );
+    }
+
+    fn rgb_of(color: &Color) -> (u8, u8, u8) {
+        let channel = |v: f64| (v * 255.0).round() as u8;
+        (
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue),
+        )
+    }
+
+    #[test]
+    fn host_colors_are_stable() {
+        let theme = dark();
+        let overrides = vec![("nas".to_owned(), rgb(1, 2, 3))].into_iter().collect();
+        let colors = HostColors::new(
+            vec!["localhost".into(), "laptop".into(), "nas".into()],
+            overrides,
+        );
+
+        assert_eq!(theme.series[0].red, colors.color("localhost", &theme).red);
+        assert_eq!(theme.series[1].red, colors.color("laptop", &theme).red);
+        assert_eq!(rgb(1, 2, 3).red, colors.color("nas", &theme).red);
+        assert_eq!(
+            colors.color("other", &theme).red,
+            colors.color("other", &theme).red
+        );
+
+        // Hash of long names wraps around
+        let long = "build-server-0123456789.example.org";
+        let color = colors.color(long, &theme);
+        assert!(theme.series.iter().any(|c| c.red == color.red));
+        assert_eq!(color.red, colors.color(long, &theme).red);
+        let backup = colors.color("backup", &theme);
+        assert_eq!((255, 225, 25), rgb_of(&backup));
+        let other = HostColors::new(vec!["localhost".into()], BTreeMap::new());
+        assert_eq!((255, 225, 25), rgb_of(&other.color("backup", &theme)));
+
+        let mut hosts = vec!["zeta", "nas", "alpha", "localhost"];
+        colors.sort(&mut hosts);
+        assert_eq!(vec!["localhost", "nas", "alpha", "zeta"], hosts);
+    }
+}
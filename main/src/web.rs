@@ -0,0 +1,241 @@
+use display::Frame;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Requests with longer headers are rejected
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// How often the live view loads a new screenshot
+const LIVE_REFRESH_MS: u32 = 1000;
+
+/// Serves the last published frame over HTTP, as `/screenshot.png` and as a
+/// page reloading it at `/live`. There is no authentication, like for the
+/// VNC server.
+pub struct WebServer {
+    frames: watch::Sender<Option<Arc<Frame>>>,
+    local_addr: SocketAddr,
+}
+
+impl WebServer {
+    pub async fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (frames, receiver) = watch::channel(None);
+        tokio::spawn(accept(listener, receiver));
+
+        Ok(Self { frames, local_addr })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Replaces the frame served as screenshot
+    pub fn publish(&self, frame: Frame) {
+        // Fails only without receivers, the listener keeps one
+        let _ = self.frames.send(Some(Arc::new(frame)));
+    }
+}
+
+async fn accept(listener: TcpListener, frames: watch::Receiver<Option<Arc<Frame>>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, address)) => {
+                let frames = frames.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, frames).await {
+                        log::debug!("HTTP client {} failed: {}", address, e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Failed to accept HTTP client: {}", e),
+        }
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", status).into_bytes(),
+        }
+    }
+}
+
+/// Answers a single request, the connection is closed afterwards.
+async fn serve(
+    mut stream: TcpStream,
+    frames: watch::Receiver<Option<Arc<Frame>>>,
+) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    let (method, path) = loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Request::new(&mut headers);
+        match parsed.parse(&request) {
+            Ok(httparse::Status::Complete(_)) => {
+                let method = parsed.method.unwrap_or_default().to_owned();
+                let path = parsed.path.unwrap_or_default();
+                // Query is only used to avoid caching
+                let path = path.split('?').next().unwrap_or_default().to_owned();
+                break (method, path);
+            }
+            Ok(httparse::Status::Partial) if request.len() < MAX_REQUEST_SIZE => {}
+            Ok(httparse::Status::Partial) => {
+                return respond(
+                    &mut stream,
+                    Response::text("431 Request Header Fields Too Large"),
+                    true,
+                )
+                .await;
+            }
+            Err(_) => {
+                return respond(&mut stream, Response::text("400 Bad Request"), true).await;
+            }
+        }
+    };
+
+    let head_only = method == "HEAD";
+    let response = if method != "GET" && !head_only {
+        Response::text("405 Method Not Allowed")
+    } else {
+        route(&path, frames).await
+    };
+    respond(&mut stream, response, !head_only).await
+}
+
+async fn route(path: &str, frames: watch::Receiver<Option<Arc<Frame>>>) -> Response {
+    match path {
+        "/screenshot.png" => {
+            let frame = match frames.borrow().clone() {
+                Some(frame) => frame,
+                None => return Response::text("503 Service Unavailable"),
+            };
+            let png = tokio::task::spawn_blocking(move || {
+                let mut png = Vec::new();
+                frame.write_png(&mut png).map(|_| png)
+            })
+            .await;
+            match png {
+                Ok(Ok(body)) => Response {
+                    status: "200 OK",
+                    content_type: "image/png",
+                    body,
+                },
+                Ok(Err(e)) => {
+                    log::warn!("Failed to encode screenshot: {:?}", e);
+                    Response::text("500 Internal Server Error")
+                }
+                Err(e) => {
+                    log::warn!("Failed to encode screenshot: {}", e);
+                    Response::text("500 Internal Server Error")
+                }
+            }
+        }
+        "/" | "/live" => Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            body: live_page().into_bytes(),
+        },
+        _ => Response::text("404 Not Found"),
+    }
+}
+
+/// Shows the screenshot and replaces it once the next one is loaded, so it
+/// doesn't flicker.
+fn live_page() -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>fb4rasp</title>
+<style>body {{ margin: 0; background: #000; }} img {{ display: block; margin: auto; max-width: 100vw; max-height: 100vh; }}</style>
+</head>
+<body>
+<img id="screen" src="/screenshot.png" alt="screen">
+<script>
+const screen = document.getElementById("screen");
+function refresh() {{
+  const next = new Image();
+  next.onload = () => {{ screen.src = next.src; setTimeout(refresh, {refresh}); }};
+  next.onerror = () => setTimeout(refresh, {refresh});
+  next.src = "/screenshot.png?t=" + Date.now();
+}}
+setTimeout(refresh, {refresh});
+</script>
+</body>
+</html>
+"#,
+        refresh = LIVE_REFRESH_MS
+    )
+}
+
+async fn respond(stream: &mut TcpStream, response: Response, body: bool) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if body {
+        stream.write_all(&response.body).await?;
+    }
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[tokio::test]
+    async fn pages_are_routed() {
+        let server = WebServer::bind(([127, 0, 0, 1], 0).into()).await.unwrap();
+        let addr = server.local_addr();
+
+        assert!(get(addr, "/screenshot.png")
+            .await
+            .starts_with("HTTP/1.1 503 "));
+        let live = get(addr, "/live").await;
+        assert!(live.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(live.contains("/screenshot.png?t="));
+        assert!(get(addr, "/missing").await.starts_with("HTTP/1.1 404 "));
+
+        server.publish(Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![0x00_ff_00_00; 4],
+        });
+        let screenshot = get(addr, "/screenshot.png?t=1").await;
+        assert!(screenshot.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(screenshot.contains("Content-Type: image/png\r\n"));
+    }
+}
@@ -0,0 +1,895 @@
+use crate::helpers::{self, ChartColors, PlotData, SeriesData, SummaryMemUsage};
+use crate::layout::{Grid, Size, Stack, Widget};
+use crate::theme::{HostColors, Theme};
+use display::{Color, Display, ImageStyle, Point, Rect, TextLayout};
+use engine::{pages::Pages, params::Layout, ring_buffer::FixedRingBuffer};
+use fb4rasp_shared::{CpuUsage, MemInfo, SystemInfo};
+use std::cmp::max;
+use std::collections::HashMap;
+
+/// Everything widgets show, collected once per frame.
+pub struct Snapshot {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub cpu: CpuUsage,
+    pub cpu_temperature: f32,
+    pub mem: MemInfo,
+    pub net: NetSnapshot,
+    pub touches: Vec<adafruit_mpr121::Mpr121TouchStatus>,
+    /// History of all hosts
+    pub system_infos: HashMap<String, FixedRingBuffer<SystemInfo>>,
+    /// History of transferred bytes per refresh period
+    pub net_tx: Vec<i64>,
+    pub net_rx: Vec<i64>,
+    pub pages: Pages,
+    /// Colors for widgets without their own
+    pub theme: Theme,
+    pub host_colors: HostColors,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NetSnapshot {
+    pub tx_bytes: i64,
+    pub rx_bytes: i64,
+    /// Bytes per second
+    pub tx_rate: i64,
+    pub rx_rate: i64,
+}
+
+/// Font role of widgets without their own font
+pub const DEFAULT_FONT: &str = "mono";
+
+/// Above this CPU temperature (in °C) its line uses the alert color
+const CPU_TEMPERATURE_ALERT: f32 = 80.0;
+/// Average CPU usage (in %) of a host shown as alert above this
+const CPU_LOAD_ALERT: f32 = 90.0;
+/// Share of used memory of a host shown as alert above this
+const MEMORY_ALERT: f64 = 0.9;
+
+#[derive(Debug, Clone)]
+pub struct TextStyle {
+    pub font: String,
+    pub font_size: f64,
+    /// Color of the theme used by the widget if not set
+    pub color: Option<Color>,
+}
+
+impl TextStyle {
+    fn new(font_size: f64) -> Self {
+        Self {
+            font: DEFAULT_FONT.to_owned(),
+            font_size,
+            color: None,
+        }
+    }
+
+    fn color_or<'a>(&'a self, theme_color: &'a Color) -> &'a Color {
+        self.color.as_ref().unwrap_or(theme_color)
+    }
+}
+
+fn format_bytes(bytes: i64) -> String {
+    size::Size::Bytes(bytes).to_string(size::Base::Base2, size::Style::Smart)
+}
+
+fn format_kibibytes(kibibytes: u64) -> String {
+    size::Size::Kibibytes(kibibytes).to_string(size::Base::Base2, size::Style::Smart)
+}
+
+fn text_height<DB>(fb: &mut DB, style: &TextStyle, lines: usize) -> f64
+where
+    for<'a> DB: Display<'a>,
+{
+    if lines == 0 {
+        return 0.0;
+    }
+
+    fb.set_font(&style.font);
+    fb.set_font_size(style.font_size);
+    let metrics = fb.font_metrics();
+    metrics.ascent + metrics.descent + (lines - 1) as f64 * metrics.line_height
+}
+
+/// Renders lines from the top of `area`, too long ones are shortened.
+fn render_lines<DB>(fb: &mut DB, area: &Rect, style: &TextStyle, color: &Color, lines: &[String])
+where
+    for<'a> DB: Display<'a>,
+{
+    fb.set_font(&style.font);
+    fb.set_font_size(style.font_size);
+    fb.set_color(color);
+    let layout = TextLayout {
+        ellipsis: true,
+        ..TextLayout::default()
+    };
+    for (i, line) in lines.iter().enumerate() {
+        let rect = Rect {
+            y: area.y + i as f64 * fb.font_metrics().line_height,
+            height: 0.0,
+            ..area.clone()
+        };
+        fb.render_text_box(&rect, line, &layout);
+    }
+}
+
+/// Current date and time
+pub struct Clock {
+    /// See `chrono::format::strftime`
+    pub format: String,
+    pub style: TextStyle,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            format: "%a, %d.%m.%Y, %H:%M:%S".to_owned(),
+            style: TextStyle::new(22.0),
+        }
+    }
+}
+
+impl<DB> Widget<DB> for Clock
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, _data: &Snapshot) -> f64 {
+        text_height(fb, &self.style, 1)
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let text = data.time.format(&self.format).to_string();
+        let color = self.style.color_or(&data.theme.clock);
+        render_lines(fb, area, &self.style, color, &[text]);
+    }
+}
+
+/// Average and per core CPU usage of a host, the local one also shows its
+/// temperature and uses the alert color if it is too high
+pub struct CpuLine {
+    /// Local host if not set
+    pub host: Option<String>,
+    pub style: TextStyle,
+}
+
+impl Default for CpuLine {
+    fn default() -> Self {
+        Self {
+            host: None,
+            style: TextStyle::new(18.0),
+        }
+    }
+}
+
+/// Latest readings of given host, none if it didn't report anything yet
+fn host_info<'a>(data: &'a Snapshot, host: &str) -> Option<&'a SystemInfo> {
+    data.system_infos
+        .get(host)
+        .filter(|infos| infos.size() > 0)
+        .map(|infos| infos.last())
+}
+
+impl<DB> Widget<DB> for CpuLine
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, _data: &Snapshot) -> f64 {
+        text_height(fb, &self.style, 1)
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let cpu = match &self.host {
+            Some(host) => match host_info(data, host) {
+                Some(info) => &info.cpu,
+                None => return,
+            },
+            None => &data.cpu,
+        };
+        let cores: Vec<String> = cpu.detailed.iter().map(|u| format!("{:>2.0}", u)).collect();
+        let text = match &self.host {
+            Some(host) => format!("{}: {:>2.0}% [{}]", host, cpu.avg, cores.join(", ")),
+            None => format!(
+                "CPU: {:>2.0}% [{}] ({:.1}°C)",
+                cpu.avg,
+                cores.join(", "),
+                data.cpu_temperature
+            ),
+        };
+        let color = if self.host.is_none() && data.cpu_temperature > CPU_TEMPERATURE_ALERT {
+            &data.theme.alert
+        } else {
+            self.style.color_or(&data.theme.cpu)
+        };
+        render_lines(fb, area, &self.style, color, &[text]);
+    }
+}
+
+/// Used and total memory of a host
+pub struct MemLine {
+    /// Local host if not set
+    pub host: Option<String>,
+    pub style: TextStyle,
+}
+
+impl Default for MemLine {
+    fn default() -> Self {
+        Self {
+            host: None,
+            style: TextStyle::new(18.0),
+        }
+    }
+}
+
+impl<DB> Widget<DB> for MemLine
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, _data: &Snapshot) -> f64 {
+        text_height(fb, &self.style, 1)
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let (mem, label) = match &self.host {
+            Some(host) => match host_info(data, host) {
+                Some(info) => (&info.mem, host.as_str()),
+                None => return,
+            },
+            None => (&data.mem, "Memory"),
+        };
+        let text = format!(
+            "{}: {} / {}",
+            label,
+            format_kibibytes(mem.used_mem),
+            format_kibibytes(mem.total_mem),
+        );
+        let color = self.style.color_or(&data.theme.mem);
+        render_lines(fb, area, &self.style, color, &[text]);
+    }
+}
+
+/// Transmitted and received bytes of the router, each in its own line
+pub struct NetCounters {
+    pub tx: TextStyle,
+    pub rx: TextStyle,
+    /// Name of an image shown left of both lines, if it is loaded
+    pub icon: Option<String>,
+}
+
+impl Default for NetCounters {
+    fn default() -> Self {
+        Self {
+            tx: TextStyle::new(14.0),
+            rx: TextStyle::new(14.0),
+            icon: Some("network".to_owned()),
+        }
+    }
+}
+
+impl<DB> Widget<DB> for NetCounters
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, _data: &Snapshot) -> f64 {
+        text_height(fb, &self.tx, 1) + text_height(fb, &self.rx, 1)
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let mut area = area.clone();
+        if let Some(icon) = &self.icon {
+            let style = ImageStyle {
+                height: Some(self.preferred_height(fb, area.width, data)),
+                ..ImageStyle::default()
+            };
+            if let Some(size) = fb.render_image(
+                &Point {
+                    x: area.x,
+                    y: area.y,
+                },
+                icon,
+                &style,
+            ) {
+                area.x += size.width + 4.0;
+                area.width = (area.width - size.width - 4.0).max(0.0);
+            }
+        }
+
+        let net = &data.net;
+        let tx = format!(
+            "Bytes tx: {}, tx/s: {}",
+            format_bytes(net.tx_bytes),
+            format_bytes(net.tx_rate),
+        );
+        render_lines(fb, &area, &self.tx, self.tx.color_or(&data.theme.tx), &[tx]);
+
+        area.y += text_height(fb, &self.tx, 1);
+        let rx = format!(
+            "Bytes rx: {}, rx/s: {}",
+            format_bytes(net.rx_bytes),
+            format_bytes(net.rx_rate),
+        );
+        render_lines(fb, &area, &self.rx, self.rx.color_or(&data.theme.rx), &[rx]);
+    }
+}
+
+/// Pins of the MPR121 touched since the last frame, a line per reading
+pub struct TouchStatus {
+    pub style: TextStyle,
+}
+
+impl Default for TouchStatus {
+    fn default() -> Self {
+        Self {
+            style: TextStyle::new(10.0),
+        }
+    }
+}
+
+impl TouchStatus {
+    fn lines(data: &Snapshot) -> Vec<String> {
+        data.touches
+            .iter()
+            .map(|ts| {
+                let pins: Vec<String> = (adafruit_mpr121::Mpr121TouchStatus::first()
+                    ..=adafruit_mpr121::Mpr121TouchStatus::last())
+                    .filter(|i| ts.touched(*i))
+                    .map(|i| format!("{}", i))
+                    .collect();
+                format!("Touched pins: {}", pins.join(", "))
+            })
+            .collect()
+    }
+}
+
+impl<DB> Widget<DB> for TouchStatus
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, data: &Snapshot) -> f64 {
+        text_height(fb, &self.style, data.touches.len())
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let color = self.style.color_or(&data.theme.touch);
+        render_lines(fb, area, &self.style, color, &Self::lines(data));
+    }
+}
+
+/// Readings which need attention, a line each: high CPU temperature of the
+/// local host and high CPU or memory usage of any host
+pub struct Alerts {
+    pub style: TextStyle,
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self {
+            style: TextStyle::new(18.0),
+        }
+    }
+}
+
+impl Alerts {
+    fn lines(data: &Snapshot) -> Vec<String> {
+        let mut lines = Vec::new();
+        if data.cpu_temperature > CPU_TEMPERATURE_ALERT {
+            lines.push(format!("CPU temperature: {:.1}°C", data.cpu_temperature));
+        }
+
+        let mut hosts: Vec<&String> = data.system_infos.keys().collect();
+        data.host_colors.sort(&mut hosts);
+        for host in hosts {
+            let info = match host_info(data, host) {
+                Some(info) => info,
+                None => continue,
+            };
+            if info.cpu.avg > CPU_LOAD_ALERT {
+                lines.push(format!("{}: CPU {:.0}%", host, info.cpu.avg));
+            }
+            let mem = &info.mem;
+            if mem.total_mem > 0 && mem.used_mem as f64 > mem.total_mem as f64 * MEMORY_ALERT {
+                lines.push(format!(
+                    "{}: memory {:.0}%",
+                    host,
+                    mem.used_mem as f64 * 100.0 / mem.total_mem as f64
+                ));
+            }
+        }
+
+        lines
+    }
+}
+
+impl<DB> Widget<DB> for Alerts
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, fb: &mut DB, _width: f64, data: &Snapshot) -> f64 {
+        text_height(fb, &self.style, max(Self::lines(data).len(), 1))
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let lines = Self::lines(data);
+        if lines.is_empty() {
+            let color = self.style.color_or(&data.theme.labels);
+            render_lines(fb, area, &self.style, color, &["No alerts".to_owned()]);
+        } else {
+            let color = self.style.color_or(&data.theme.alert);
+            render_lines(fb, area, &self.style, color, &lines);
+        }
+    }
+}
+
+/// Dot per page, the one of the page shown is filled. Nothing is drawn if
+/// there is a single page.
+pub struct PageIndicator {
+    /// Color of the theme if not set
+    pub color: Option<Color>,
+    pub radius: f64,
+}
+
+impl Default for PageIndicator {
+    fn default() -> Self {
+        Self {
+            color: None,
+            radius: 3.0,
+        }
+    }
+}
+
+impl<DB> Widget<DB> for PageIndicator
+where
+    for<'a> DB: Display<'a>,
+{
+    fn preferred_height(&self, _fb: &mut DB, _width: f64, data: &Snapshot) -> f64 {
+        if data.pages.len() < 2 {
+            0.0
+        } else {
+            4.0 * self.radius
+        }
+    }
+
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        let count = data.pages.len();
+        if count < 2 {
+            return;
+        }
+
+        let step = 3.0 * self.radius;
+        let left = area.x + (area.width - (count - 1) as f64 * step) / 2.0;
+        fb.set_color(self.color.as_ref().unwrap_or(&data.theme.pages));
+        fb.set_line_width(1.0);
+        for i in 0..count {
+            let center = Point {
+                x: left + i as f64 * step,
+                y: area.y + area.height / 2.0,
+            };
+            if i == data.pages.current() {
+                fb.fill_circle(&center, self.radius);
+            } else {
+                fb.stroke_circle(&center, self.radius - 0.5);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartKind {
+    /// CPU (left axis) and used memory (right axis) of all hosts
+    SystemLoad,
+    /// Transmitted (left axis) and received (right axis) bytes of the router
+    Network,
+}
+
+pub struct Chart {
+    pub kind: ChartKind,
+    /// Color of axis labels, the one of the theme if not set
+    pub color: Option<Color>,
+    /// Hosts shown by the system load chart, all if not set
+    pub hosts: Option<Vec<String>>,
+}
+
+impl Chart {
+    pub fn new(kind: ChartKind) -> Self {
+        Self {
+            kind,
+            color: None,
+            hosts: None,
+        }
+    }
+
+    fn colors(&self, theme: &Theme) -> ChartColors {
+        ChartColors {
+            labels: rgba(self.color.as_ref().unwrap_or(&theme.labels)),
+            axis: rgba(&theme.axis),
+            legend: rgba(&theme.legend),
+        }
+    }
+
+    fn render_system_load<DB>(&self, fb: &mut DB, area: &Rect, data: &Snapshot)
+    where
+        for<'a> DB: Display<'a>,
+    {
+        let mut cpu_axis_data = Vec::<SeriesData<Vec<f32>>>::new();
+        let mut mem_axis_data = Vec::<SeriesData<SummaryMemUsage>>::new();
+        let mut max_mem: u64 = 0;
+        let mut names: Vec<&String> = data
+            .system_infos
+            .keys()
+            .filter(|name| match &self.hosts {
+                Some(hosts) => hosts.contains(name),
+                None => true,
+            })
+            .collect();
+        // Same order of series (and their legend) on every render
+        data.host_colors.sort(&mut names);
+        for name in names {
+            let frb_si = &data.system_infos[name];
+            let color = rgba(&data.host_colors.color(name, &data.theme));
+
+            let cpu_usage: Vec<f32> = frb_si.iter().map(|x| x.cpu.avg).collect();
+            let mem_data: Vec<MemInfo> = frb_si.iter().map(|x| x.mem).collect();
+
+            cpu_axis_data.push(SeriesData {
+                data: cpu_usage,
+                name: name.to_owned(),
+                color,
+            });
+
+            let smu = SummaryMemUsage {
+                ram: mem_data.iter().map(|mu| mu.used_mem).collect(),
+                swap: mem_data.iter().map(|mu| mu.used_swap).collect(),
+                total_ram: mem_data[0].total_mem,
+                total_swap: mem_data[0].total_swap,
+            };
+            max_mem = max(max_mem, *smu.ram.iter().max().unwrap());
+            mem_axis_data.push(SeriesData {
+                data: smu,
+                name: name.to_owned(),
+                color,
+            });
+        }
+
+        let left_axis = PlotData {
+            data: cpu_axis_data,
+            y_range: 0.0..100.0f32,
+            formatter: |v| format!("{:.0}%", v),
+        };
+        let right_axis = PlotData {
+            data: mem_axis_data,
+            y_range: 0..max_mem,
+            formatter: |v| format_kibibytes(*v),
+        };
+
+        let plot = plot_area(fb, area);
+        helpers::plot_data(&plot, &self.colors(&data.theme), left_axis, right_axis);
+    }
+
+    fn render_network<DB>(&self, fb: &mut DB, area: &Rect, data: &Snapshot)
+    where
+        for<'a> DB: Display<'a>,
+    {
+        if data.net_tx.is_empty() || data.net_rx.is_empty() {
+            return;
+        }
+
+        let tx_max: i64 = *data.net_tx.iter().max().unwrap();
+        let rx_max: i64 = *data.net_rx.iter().max().unwrap();
+
+        let left_axis = PlotData {
+            data: vec![SeriesData {
+                data: data.net_tx.clone(),
+                name: "localhost".to_owned(),
+                color: rgba(&data.theme.tx),
+            }],
+            y_range: 0..tx_max,
+            formatter: |v| format_bytes(*v),
+        };
+        let right_axis = PlotData {
+            data: vec![SeriesData {
+                data: data.net_rx.clone(),
+                name: "localhost".to_owned(),
+                color: rgba(&data.theme.rx),
+            }],
+            y_range: 0..rx_max,
+            formatter: |v| format_bytes(*v),
+        };
+
+        let plot = plot_area(fb, area);
+        helpers::plot_data(&plot, &self.colors(&data.theme), left_axis, right_axis);
+    }
+}
+
+fn rgba(color: &Color) -> plotters::style::RGBAColor {
+    let channel = |v: f64| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+    plotters::style::RGBAColor(
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue),
+        color.alpha,
+    )
+}
+
+/// Plotters drawing area covering `area` of the display
+fn plot_area<'a, DB>(
+    fb: &'a DB,
+    area: &Rect,
+) -> plotters::drawing::DrawingArea<<DB as Display<'a>>::DrawingBackend, plotters::coord::Shift>
+where
+    for<'b> DB: Display<'b>,
+{
+    use plotters::prelude::IntoDrawingArea;
+
+    fb.get_backend().unwrap().into_drawing_area().shrink(
+        (area.x.round() as i32, area.y.round() as i32),
+        (area.width.round() as u32, area.height.round() as u32),
+    )
+}
+
+impl<DB> Widget<DB> for Chart
+where
+    for<'a> DB: Display<'a>,
+{
+    fn render(&self, fb: &mut DB, area: &Rect, data: &Snapshot) {
+        if area.width < 1.0 || area.height < 1.0 {
+            return;
+        }
+
+        match self.kind {
+            ChartKind::SystemLoad => self.render_system_load(fb, area, data),
+            ChartKind::Network => self.render_network(fb, area, data),
+        }
+    }
+}
+
+/// Screen shown unless configured otherwise, charts are side by side or one
+/// above the other depending on `layout`.
+pub fn default_screen<DB>(layout: Layout) -> Stack<DB>
+where
+    for<'a> DB: Display<'a> + 'static,
+{
+    let mut screen = Stack::column(0.0, 0.0);
+    screen.add(Size::Auto, Box::new(Clock::default()));
+    screen.add(Size::Auto, Box::new(CpuLine::default()));
+    screen.add(Size::Auto, Box::new(MemLine::default()));
+    screen.add(Size::Auto, Box::new(NetCounters::default()));
+    screen.add(Size::Auto, Box::new(TouchStatus::default()));
+
+    let mut charts = match layout {
+        Layout::Horizontal => Grid::new(vec![1.0, 1.0], vec![1.0], 2.0, 4.0),
+        Layout::Vertical => Grid::new(vec![1.0], vec![1.0, 1.0], 2.0, 4.0),
+    };
+    charts.add(Box::new(Chart::new(ChartKind::SystemLoad)));
+    charts.add(Box::new(Chart::new(ChartKind::Network)));
+    screen.add(Size::Weight(1.0), Box::new(charts));
+    screen.add(Size::Auto, Box::new(PageIndicator::default()));
+
+    screen
+}
+
+/// Usage of a single host with its own chart
+pub fn host_screen<DB>(host: &str) -> Stack<DB>
+where
+    for<'a> DB: Display<'a> + 'static,
+{
+    let host = Some(host.to_owned());
+    let mut screen = Stack::column(0.0, 0.0);
+    screen.add(Size::Auto, Box::new(Clock::default()));
+    screen.add(
+        Size::Auto,
+        Box::new(CpuLine {
+            host: host.clone(),
+            ..CpuLine::default()
+        }),
+    );
+    screen.add(
+        Size::Auto,
+        Box::new(MemLine {
+            host: host.clone(),
+            ..MemLine::default()
+        }),
+    );
+
+    let mut chart = Chart::new(ChartKind::SystemLoad);
+    chart.hosts = host.map(|h| vec![h]);
+    screen.add(Size::Weight(1.0), Box::new(chart));
+    screen.add(Size::Auto, Box::new(PageIndicator::default()));
+
+    screen
+}
+
+/// Router traffic with its chart
+pub fn network_screen<DB>() -> Stack<DB>
+where
+    for<'a> DB: Display<'a> + 'static,
+{
+    let mut screen = Stack::column(0.0, 0.0);
+    screen.add(Size::Auto, Box::new(Clock::default()));
+    screen.add(Size::Auto, Box::new(NetCounters::default()));
+    screen.add(Size::Weight(1.0), Box::new(Chart::new(ChartKind::Network)));
+    screen.add(Size::Auto, Box::new(PageIndicator::default()));
+
+    screen
+}
+
+/// Readings which need attention
+pub fn alerts_screen<DB>() -> Stack<DB>
+where
+    for<'a> DB: Display<'a> + 'static,
+{
+    let mut screen = Stack::column(0.0, 0.0);
+    screen.add(Size::Auto, Box::new(Clock::default()));
+    screen.add(Size::Weight(1.0), Box::new(Alerts::default()));
+    screen.add(Size::Auto, Box::new(PageIndicator::default()));
+
+    screen
+}
+
+/// Pages shown unless screens are configured: the overview, details of every
+/// host, the network and alerts, named `overview`, after the host, `network`
+/// and `alerts`.
+pub fn default_pages<DB>(layout: Layout, hosts: &[String]) -> Vec<(String, Box<dyn Widget<DB>>)>
+where
+    for<'a> DB: Display<'a> + 'static,
+{
+    let mut pages: Vec<(String, Box<dyn Widget<DB>>)> = Vec::new();
+    pages.push(("overview".to_owned(), Box::new(default_screen(layout))));
+    for host in hosts.iter() {
+        pages.push((host.clone(), Box::new(host_screen(host))));
+    }
+    pages.push(("network".to_owned(), Box::new(network_screen())));
+    pages.push(("alerts".to_owned(), Box::new(alerts_screen())));
+
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use display::{CairoImage, Frame};
+    use std::path::PathBuf;
+
+    /// Set to write the rendered pages as the new reference images
+    const UPDATE_GOLDEN: &str = "FB4RASP_UPDATE_GOLDEN";
+    const SIZE: (usize, usize) = (480, 320);
+    /// Channels may differ this much, e.g. due to antialiasing...
+    const CHANNEL_TOLERANCE: u32 = 16;
+    /// ...in at most this many pixels (per mille), e.g. due to font hinting
+    const PIXEL_TOLERANCE: usize = 5;
+
+    fn snapshot(hosts: &[String]) -> Snapshot {
+        let theme = crate::theme::builtin_themes()[crate::theme::DEFAULT_THEME].clone();
+        let system_infos = hosts
+            .iter()
+            .enumerate()
+            .map(|(h, host)| {
+                let mut infos = FixedRingBuffer::new(80, SystemInfo::default());
+                for i in 0..80 {
+                    let load = ((i * 7 + h * 13) % 100) as f32;
+                    infos.add(SystemInfo {
+                        cpu: CpuUsage {
+                            avg: load,
+                            detailed: vec![load; 4],
+                        },
+                        mem: MemInfo {
+                            // Memory of the second host is almost full
+                            used_mem: [512, 3_900][h] * 1024,
+                            total_mem: 4 * 1024 * 1024,
+                            used_swap: 0,
+                            total_swap: 1024 * 1024,
+                        },
+                    });
+                }
+                (host.clone(), infos)
+            })
+            .collect();
+
+        Snapshot {
+            time: chrono::Local
+                .with_ymd_and_hms(2021, 3, 14, 15, 9, 26)
+                .unwrap(),
+            cpu: CpuUsage {
+                avg: 42.0,
+                detailed: vec![10.0, 30.0, 50.0, 78.0],
+            },
+            cpu_temperature: 55.5,
+            mem: MemInfo {
+                used_mem: 1536 * 1024,
+                total_mem: 4 * 1024 * 1024,
+                used_swap: 128 * 1024,
+                total_swap: 1024 * 1024,
+            },
+            net: NetSnapshot {
+                tx_bytes: 123_456_789,
+                rx_bytes: 987_654_321,
+                tx_rate: 12_345,
+                rx_rate: 654_321,
+            },
+            touches: vec![],
+            system_infos,
+            net_tx: (0..80).map(|i| (i * 997) % 50_000).collect(),
+            net_rx: (0..80).map(|i| (i * 1_543) % 80_000).collect(),
+            pages: Pages::new(vec![]),
+            theme,
+            host_colors: HostColors::new(hosts.to_vec(), Default::default()),
+        }
+    }
+
+    fn differs(a: u32, b: u32) -> bool {
+        (0..3).any(|c| {
+            let (a, b) = ((a >> (8 * c)) & 0xff, (b >> (8 * c)) & 0xff);
+            a.max(b) - a.min(b) > CHANNEL_TOLERANCE
+        })
+    }
+
+    #[test]
+    fn default_pages_match_golden_images() {
+        let hosts = vec![engine::engine::DEFAULT_HOST.to_owned(), "nas".to_owned()];
+        let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("golden");
+        let update = std::env::var_os(UPDATE_GOLDEN).is_some();
+
+        for layout in [Layout::Vertical, Layout::Horizontal] {
+            let pages = default_pages::<CairoImage>(layout, &hosts);
+            let mut snapshot = snapshot(&hosts);
+            snapshot.pages = Pages::new(pages.iter().map(|(name, _)| name.clone()).collect());
+
+            for (index, (name, page)) in pages.iter().enumerate() {
+                // Only the overview depends on the layout
+                let name = match (index, layout) {
+                    (0, Layout::Vertical) => format!("{}-vertical", name),
+                    (0, Layout::Horizontal) => format!("{}-horizontal", name),
+                    (_, Layout::Vertical) => name.clone(),
+                    (_, Layout::Horizontal) => continue,
+                };
+                snapshot.pages.show(&pages[index].0);
+
+                let mut img = CairoImage::new(SIZE.0, SIZE.1).unwrap();
+                img.start();
+                img.set_font(DEFAULT_FONT);
+                img.set_color(&snapshot.theme.background);
+                img.clean();
+                let area = Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: SIZE.0 as f64,
+                    height: SIZE.1 as f64,
+                };
+                page.render(&mut img, &area, &snapshot);
+                img.finish();
+                let frame = img.frame().unwrap();
+
+                let path = golden.join(format!("{}.png", name));
+                if update {
+                    std::fs::create_dir_all(&golden).unwrap();
+                    let mut file = std::fs::File::create(&path).unwrap();
+                    frame.write_png(&mut file).unwrap();
+                    continue;
+                }
+
+                let mut file = std::fs::File::open(&path).unwrap_or_else(|e| {
+                    panic!(
+                        "{:?}: {}, run with {} set to create it",
+                        path, e, UPDATE_GOLDEN
+                    )
+                });
+                let expected = Frame::read_png(&mut file).unwrap();
+                assert_eq!(
+                    (expected.width, expected.height),
+                    (frame.width, frame.height)
+                );
+                let different = expected
+                    .pixels
+                    .iter()
+                    .zip(frame.pixels.iter())
+                    .filter(|(a, b)| differs(**a, **b))
+                    .count();
+                assert!(
+                    different * 1000 <= frame.pixels.len() * PIXEL_TOLERANCE,
+                    "Page {} differs from {:?} in {} pixels, run with {} set to update it",
+                    name,
+                    path,
+                    different,
+                    UPDATE_GOLDEN
+                );
+            }
+        }
+    }
+}